@@ -0,0 +1,479 @@
+use std::collections::BTreeMap;
+use std::error::Error;
+use std::future::Future;
+use std::io::{self, Write};
+use std::path::Path;
+use std::{env, fs, process};
+
+use rcvs_web::model;
+use rcvs_web_client::{AlternativeEdit, AuditEntry, BallotRecord, Client};
+use serde::Serialize;
+use serde_json::{json, Map, Value};
+
+const USAGE: &str = "Usage: rcvs-admin [options] <command> [arguments]
+
+Options:
+    --url <url>          Address of the running server, e.g. http://localhost:8080
+    --database <file>    Data base of the election (default: model.db)
+    --config <file>      Configuration of the election (default: election.json)
+    --force              Writes to the data base of a closed election anyway
+
+Commands:
+    init <title> <alternative>...      Writes a new configuration
+    config <key> [<value>]             Shows or sets a configuration entry
+    alternatives [list]                Lists the alternatives
+    alternatives add <name> [<description>]
+    alternatives remove <name>         Also removes the ranks it was given
+    roll [list]                        Lists the voter roll as CSV
+    roll import <file>                 Enrolls the voters of a CSV roll
+    open                               Opens the election (API)
+    close                              Closes the election and draws the winners (API)
+    ballots export [<file>]            Writes the ballots as CSV
+    ballots import <file>              Adds the ballots of a CSV export
+    stats                              Shows the turnout
+    verify                             Draws the winners again from the recorded seed
+    audit [list]                       Writes the audit log as JSON Lines
+    audit verify                       Checks the hash chain of the audit log
+    backup <file>                      Copies the data base
+
+Commands with an API route use it when --url is given, and the data base
+otherwise. Administration routes are only allowed from the loopback interface;
+the data base of a running server is better edited through them, so that the
+server notifies its webhooks.";
+
+struct Options {
+    url: Option<String>,
+    database: String,
+    config: String,
+    force: bool,
+}
+
+/// Sends a request to the server from an Actix system
+fn call<T, F, R>(options: &Options, request: R) -> Result<T, Box<dyn Error>>
+where
+    R: FnOnce(Client) -> F + 'static,
+    F: Future<Output = Result<T, rcvs_web_client::Error>> + 'static,
+{
+    let url = match &options.url {
+        Some(url) => url.to_string(),
+        None => {
+            return Err("This command needs the address of the server, given with --url".into())
+        }
+    };
+    // The client can only be created from within the system
+    let response = actix_rt::System::new("rcvs-admin")
+        .block_on(async move { request(Client::new(&url)).await });
+    Ok(response?)
+}
+
+fn read_config(options: &Options) -> Result<Map<String, Value>, Box<dyn Error>> {
+    let config = fs::read_to_string(&options.config)
+        .map_err(|what| format!("Failed to read {}: {}", options.config, what))?;
+    match serde_json::from_str(&config)? {
+        Value::Object(map) => Ok(map),
+        _ => Err(format!("{} is not a JSON object", options.config).into()),
+    }
+}
+
+fn write_config(options: &Options, config: &Map<String, Value>) -> Result<(), Box<dyn Error>> {
+    fs::write(
+        &options.config,
+        serde_json::to_string_pretty(config)? + "\n",
+    )?;
+    Ok(())
+}
+
+/// The configuration tells how ballots are stored
+fn database(options: &Options) -> Result<model::DatabaseConnection, Box<dyn Error>> {
+    let anonymous = read_config(options)?
+        .get("anonymous")
+        .and_then(Value::as_bool)
+        .unwrap_or(false);
+    model::DatabaseConnection::open(&options.database, anonymous)
+        .map_err(|what| format!("Failed to open {}: {}", options.database, what).into())
+}
+
+/// Changing the ballots, alternatives or weights of a closed election would
+/// make its recorded draw fail `verify`, so it must be reopened first
+fn writable_database(options: &Options) -> Result<model::DatabaseConnection, Box<dyn Error>> {
+    let connection = database(options)?;
+    if !options.force && model::get_final_draw(&connection)?.is_some() {
+        return Err(
+            "The election is closed; reopen it before changing it, or give --force to change its draw's data anyway"
+                .into(),
+        );
+    }
+    Ok(connection)
+}
+
+fn init(options: &Options, title: &str, alternatives: &[&str]) -> Result<(), Box<dyn Error>> {
+    if Path::new(&options.config).exists() {
+        return Err(format!("{} already exists", options.config).into());
+    }
+    let alternatives: Vec<Value> = alternatives
+        .iter()
+        .map(|name| json!({ "id": name, "description": "", "icon": "" }))
+        .collect();
+    let config = json!({ "title": title, "alternatives": alternatives });
+    match config {
+        Value::Object(map) => write_config(options, &map),
+        _ => unreachable!(),
+    }
+}
+
+fn show_config(options: &Options, key: &str) -> Result<(), Box<dyn Error>> {
+    match read_config(options)?.get(key) {
+        Some(value) => println!("{}", serde_json::to_string_pretty(value)?),
+        None => return Err(format!("{} is not set", key).into()),
+    }
+    Ok(())
+}
+
+/// Values which are not JSON are taken as strings
+fn set_config(options: &Options, key: &str, value: &str) -> Result<(), Box<dyn Error>> {
+    let mut config = read_config(options)?;
+    let value = serde_json::from_str(value).unwrap_or_else(|_| Value::String(value.to_string()));
+    config.insert(key.to_string(), value);
+    write_config(options, &config)?;
+    if Path::new(&options.database).exists() {
+        // Only the key, since values such as the mail settings hold passwords
+        model::log_event(
+            &mut database(options)?,
+            "config_changed",
+            &json!({ "key": key }),
+        )?;
+        eprintln!("The server reads its configuration on start; restart it to apply the change");
+    }
+    Ok(())
+}
+
+fn list_alternatives(options: &Options) -> Result<(), Box<dyn Error>> {
+    let alternatives = match &options.url {
+        Some(_) => call(options, |c| async move { c.get_info().await })?.alternatives,
+        None => model::get_alternatives(&mut database(options)?)?,
+    };
+    for alternative in alternatives {
+        println!("{}\t{}", alternative.id, alternative.name);
+    }
+    Ok(())
+}
+
+fn add_alternative(options: &Options, name: &str, description: &str) -> Result<(), Box<dyn Error>> {
+    let id = match &options.url {
+        Some(_) => {
+            let alternative = AlternativeEdit {
+                name: name.to_string(),
+                description: description.to_string(),
+            };
+            call(
+                options,
+                |c| async move { c.add_alternative(&alternative).await },
+            )?
+            .id
+        }
+        None => model::add_alternative(&mut writable_database(options)?, name, description)?,
+    };
+    println!("{}\t{}", id, name);
+    Ok(())
+}
+
+fn remove_alternative(options: &Options, name: &str) -> Result<(), Box<dyn Error>> {
+    let alternatives = match &options.url {
+        Some(_) => call(options, |c| async move { c.get_info().await })?.alternatives,
+        None => model::get_alternatives(&mut database(options)?)?,
+    };
+    let id = match alternatives.iter().find(|a| a.name == name) {
+        Some(alternative) => alternative.id,
+        None => return Err(format!("No alternative is named {}", name).into()),
+    };
+    match &options.url {
+        Some(_) => call(
+            options,
+            move |c| async move { c.remove_alternative(id).await },
+        ),
+        None => {
+            model::remove_alternative(&mut writable_database(options)?, id)?;
+            Ok(())
+        }
+    }
+}
+
+fn print_csv<T: Serialize>(records: &[T]) -> Result<(), Box<dyn Error>> {
+    let mut writer = csv::Writer::from_writer(io::stdout());
+    for record in records {
+        writer.serialize(record)?;
+    }
+    writer.flush()?;
+    Ok(())
+}
+
+fn list_roll(options: &Options) -> Result<(), Box<dyn Error>> {
+    let roll = match &options.url {
+        Some(_) => call(options, |c| async move { c.get_roll().await })?,
+        None => model::get_roll(&database(options)?)?,
+    };
+    print_csv(&roll)
+}
+
+fn import_roll(options: &Options, file: &str) -> Result<(), Box<dyn Error>> {
+    let roll = fs::read_to_string(file)?;
+    let credentials = match &options.url {
+        Some(_) => call(options, |c| async move { c.import_roll(&roll).await })?,
+        None => {
+            let mut entries = Vec::new();
+            for entry in csv::Reader::from_reader(roll.as_bytes()).deserialize() {
+                entries.push(entry.map_err(|what| format!("Bad voter roll: {}", what))?);
+            }
+            model::import_roll(&mut writable_database(options)?, &entries)?
+        }
+    };
+    print_csv(&credentials)
+}
+
+fn export_ballots(options: &Options, file: Option<&str>) -> Result<(), Box<dyn Error>> {
+    let mut output: Box<dyn io::Write> = match file {
+        Some(file) => Box::new(fs::File::create(file)?),
+        None => Box::new(io::stdout()),
+    };
+    if options.url.is_some() {
+        let csv = call(options, |c| async move { c.export_ballots().await })?;
+        output.write_all(csv.as_bytes())?;
+        return Ok(());
+    }
+    let mut writer = csv::Writer::from_writer(output);
+    for record in model::export_ballots(&mut database(options)?)? {
+        writer.serialize(record)?;
+    }
+    writer.flush()?;
+    Ok(())
+}
+
+fn import_ballots(options: &Options, file: &str) -> Result<(), Box<dyn Error>> {
+    let mut connection = writable_database(options)?;
+    let ids: BTreeMap<String, usize> = model::get_alternatives(&mut connection)?
+        .into_iter()
+        .map(|a| (a.name, a.id as usize))
+        .collect();
+    let mut ballots = BTreeMap::<usize, model::WeightedBallot>::new();
+    for line in csv::Reader::from_path(file)?.deserialize() {
+        let line: BallotRecord = line?;
+        let alternative = match ids.get(&line.alternative) {
+            Some(&id) => id,
+            None => return Err(format!("No alternative is named {}", line.alternative).into()),
+        };
+        if line.min > line.max {
+            return Err(format!("[{}, {}] is not a valid range", line.min, line.max).into());
+        }
+        ballots
+            .entry(line.ballot)
+            .or_insert_with(|| model::WeightedBallot {
+                weight: line.weight,
+                rows: Vec::new(),
+            })
+            .rows
+            .push(model::BallotRow {
+                elector: None,
+                alternative: alternative,
+                min: line.min,
+                max: line.max,
+            });
+    }
+    let ballots: Vec<model::WeightedBallot> = ballots.into_iter().map(|(_, b)| b).collect();
+    model::import_ballots(&mut connection, &ballots)?;
+    println!("Imported {} ballots", ballots.len());
+    Ok(())
+}
+
+fn stats(options: &Options) -> Result<(), Box<dyn Error>> {
+    let mut connection = database(options)?;
+    let data = model::collect_votes(&mut connection)?;
+    let roll = model::get_roll(&connection)?;
+
+    println!("Alternatives: {}", data.alternatives.len());
+    println!(
+        "Ballots: {} (total weight {})",
+        data.ballots.len(),
+        model::total_weight(&data.ballots)
+    );
+    if !roll.is_empty() {
+        let voted = roll.iter().filter(|e| e.voted).count();
+        println!(
+            "Turnout: {} of {} enrolled voters ({:.1} %)",
+            voted,
+            roll.len(),
+            100.0 * voted as f64 / roll.len() as f64
+        );
+    }
+    println!("Ballots ranking each alternative:");
+    for alternative in &data.alternatives {
+        let count = data
+            .ballots
+            .iter()
+            .filter(|b| {
+                b.rows
+                    .iter()
+                    .any(|r| r.alternative == alternative.id as usize)
+            })
+            .count();
+        println!("    {}\t{}", alternative.name, count);
+    }
+    Ok(())
+}
+
+/// Recomputes the duel graph from the ballots, and draws the winners again
+/// from the seed recorded when the election was closed
+fn verify(options: &Options) -> Result<(), Box<dyn Error>> {
+    let mut connection = database(options)?;
+    let (seed, recorded) = match model::get_final_draw(&connection)? {
+        Some(draw) => draw,
+        None => return Err("No winner was drawn; is the election closed?".into()),
+    };
+    let result = match &options.url {
+        Some(_) => call(options, |c| async move { c.get_result().await })?,
+        None => recorded.clone(),
+    };
+    if result.winners != recorded.winners {
+        return Err("The published winners are not those of the recorded draw".into());
+    }
+    let data = model::collect_votes(&mut connection)?;
+
+    let alternatives: Vec<usize> = data.alternatives.iter().map(|a| a.id as usize).collect();
+    let graph = data.duel_graph(&alternatives);
+    let mut arrows = Vec::new();
+    for (i, alternative) in graph.get_vertices().iter().enumerate() {
+        for (j, other) in graph.get_vertices().iter().enumerate() {
+            if i != j && graph[(i, j)] {
+                arrows.push((*alternative, *other));
+            }
+        }
+    }
+    let mut published: Vec<(usize, usize)> = result.arrows.iter().map(|a| (a.from, a.to)).collect();
+    arrows.sort();
+    published.sort();
+    if arrows != published {
+        return Err("The published duel graph does not match the ballots".into());
+    }
+
+    let winners = rcvs_web::replay_draw(&data, recorded.seats, &seed)?;
+    for (seat, &winner) in result.winners.iter().enumerate() {
+        let name = result.get_name(winner).unwrap_or_default();
+        match winners.get(seat) {
+            Some(&w) if w == winner => println!("Seat {}: {}", seat + 1, name),
+            _ => {
+                return Err(format!(
+                    "Seat {}: {} was not drawn again from seed {}",
+                    seat + 1,
+                    name,
+                    seed
+                )
+                .into())
+            }
+        }
+    }
+    if winners.len() != result.winners.len() {
+        return Err(format!("Seed {} draws {} winners", seed, winners.len()).into());
+    }
+    println!("The draw of seed {} is consistent with the ballots", seed);
+    Ok(())
+}
+
+fn audit_log(options: &Options) -> Result<Vec<AuditEntry>, Box<dyn Error>> {
+    match &options.url {
+        Some(_) => call(options, |c| async move { c.get_audit().await }),
+        None => model::get_audit_log(&database(options)?),
+    }
+}
+
+fn list_audit(options: &Options) -> Result<(), Box<dyn Error>> {
+    for entry in audit_log(options)? {
+        println!("{}", serde_json::to_string(&entry)?);
+    }
+    Ok(())
+}
+
+/// Entries cut off the end of the log go unnoticed, so the hash of the last
+/// entry is printed to be compared with a later check
+fn verify_audit(options: &Options) -> Result<(), Box<dyn Error>> {
+    let entries = audit_log(options)?;
+    if let Some(id) = model::verify_audit_log(&entries) {
+        return Err(format!("The audit log was altered at entry {}", id).into());
+    }
+    match entries.last() {
+        Some(last) => println!(
+            "The {} entries of the audit log are intact; the last one is {} with hash {}",
+            entries.len(),
+            last.id,
+            last.hash
+        ),
+        None => println!("The audit log is empty"),
+    }
+    Ok(())
+}
+
+fn backup(options: &Options, file: &str) -> Result<(), Box<dyn Error>> {
+    if Path::new(file).exists() {
+        return Err(format!("{} already exists", file).into());
+    }
+    model::backup(&database(options)?, file)
+}
+
+fn run(options: &Options, command: &[&str]) -> Result<(), Box<dyn Error>> {
+    match command {
+        ["init", title, alternatives @ ..] if !alternatives.is_empty() => {
+            init(options, title, alternatives)
+        }
+        ["config", key] => show_config(options, key),
+        ["config", key, value] => set_config(options, key, value),
+        ["alternatives"] | ["alternatives", "list"] => list_alternatives(options),
+        ["alternatives", "add", name] => add_alternative(options, name, ""),
+        ["alternatives", "add", name, description] => add_alternative(options, name, description),
+        ["alternatives", "remove", name] => remove_alternative(options, name),
+        ["roll"] | ["roll", "list"] => list_roll(options),
+        ["roll", "import", file] => import_roll(options, file),
+        ["open"] => call(options, |c| async move { c.open().await }),
+        ["close"] => call(options, |c| async move { c.close().await }),
+        ["ballots", "export"] => export_ballots(options, None),
+        ["ballots", "export", file] => export_ballots(options, Some(file)),
+        ["ballots", "import", file] => import_ballots(options, file),
+        ["stats"] => stats(options),
+        ["verify"] => verify(options),
+        ["audit"] | ["audit", "list"] => list_audit(options),
+        ["audit", "verify"] => verify_audit(options),
+        ["backup", file] => backup(options, file),
+        _ => Err(USAGE.into()),
+    }
+}
+
+fn main() {
+    let args: Vec<String> = env::args().skip(1).collect();
+    let mut options = Options {
+        url: None,
+        database: "model.db".to_string(),
+        config: "election.json".to_string(),
+        force: false,
+    };
+    let mut index = 0;
+    while index < args.len() && args[index].starts_with("--") {
+        if args[index] == "--force" {
+            options.force = true;
+            index += 1;
+            continue;
+        }
+        let value = match args.get(index + 1) {
+            Some(value) => value.to_string(),
+            None => break,
+        };
+        match args[index].as_str() {
+            "--url" => options.url = Some(value),
+            "--database" => options.database = value,
+            "--config" => options.config = value,
+            _ => break,
+        }
+        index += 2;
+    }
+    let command: Vec<&str> = args[index..].iter().map(|s| s.as_str()).collect();
+    if let Err(what) = run(&options, &command) {
+        eprintln!("{}", what);
+        process::exit(1);
+    }
+}
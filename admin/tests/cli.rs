@@ -0,0 +1,116 @@
+use std::fs;
+use std::path::Path;
+use std::process::{Command, Output};
+
+use actix_web::{test, App};
+
+/// Runs rcvs-admin on the election of the directory
+fn admin(directory: &Path, url: Option<&str>, arguments: &[&str]) -> Output {
+    let mut command = Command::new(env!("CARGO_BIN_EXE_rcvs-admin"));
+    command
+        .arg("--config")
+        .arg(directory.join("election.json"))
+        .arg("--database")
+        .arg(directory.join("model.db"));
+    if let Some(url) = url {
+        command.args(["--url", url]);
+    }
+    command
+        .args(arguments)
+        .output()
+        .expect("Failed to run rcvs-admin")
+}
+
+fn succeed(output: Output) -> String {
+    let stdout = String::from_utf8(output.stdout).unwrap();
+    assert!(
+        output.status.success(),
+        "rcvs-admin failed: {}{}",
+        stdout,
+        String::from_utf8_lossy(&output.stderr)
+    );
+    stdout
+}
+
+/// Three ballots whose majorities go round in a cycle, so that the winners
+/// depend on the seed
+const CYCLE: &str = "ballot,weight,alternative,min,max
+1,1.0,Pizza,3,3
+1,1.0,Sushi,2,2
+1,1.0,Salad,1,1
+2,1.0,Sushi,3,3
+2,1.0,Salad,2,2
+2,1.0,Pizza,1,1
+3,1.0,Salad,3,3
+3,1.0,Pizza,2,2
+3,1.0,Sushi,1,1
+";
+
+#[actix_rt::test]
+async fn closed_election_is_exported_and_verified() {
+    let directory = tempfile::tempdir().expect("Failed to create directory");
+    let path = directory.path();
+    succeed(admin(
+        path,
+        None,
+        &["init", "Lunch", "Pizza", "Sushi", "Salad"],
+    ));
+    succeed(admin(path, None, &["config", "seats", "2"]));
+
+    let config = path.join("election.json");
+    let database = path.join("model.db");
+    let app = rcvs_web::Server::new(config.to_str().unwrap(), database.to_str().unwrap())
+        .expect("Failed to start server");
+    let server = test::start(move || App::new().configure(|config| app.configure(config)));
+    let url = format!("http://{}", server.addr());
+
+    let cycle = path.join("cycle.csv");
+    fs::write(&cycle, CYCLE).unwrap();
+    succeed(admin(
+        path,
+        None,
+        &["ballots", "import", cycle.to_str().unwrap()],
+    ));
+    succeed(admin(path, Some(&url), &["close"]));
+
+    let exported = path.join("export.csv");
+    succeed(admin(
+        path,
+        Some(&url),
+        &["ballots", "export", exported.to_str().unwrap()],
+    ));
+    let export = fs::read_to_string(&exported).unwrap();
+    assert_eq!(export.lines().count(), CYCLE.lines().count());
+    assert!(export.contains("Pizza,3,3"));
+
+    for url in &[Some(url.as_str()), None] {
+        let output = succeed(admin(path, *url, &["verify"]));
+        assert!(output.contains("Seat 1: "), "{}", output);
+        assert!(output.contains("Seat 2: "), "{}", output);
+        assert!(
+            output.contains("is consistent with the ballots"),
+            "{}",
+            output
+        );
+    }
+
+    // The data base of a closed election is not changed without --force
+    let late = path.join("late.csv");
+    fs::write(&late, &CYCLE[..CYCLE.find("\n2,").unwrap() + 1]).unwrap();
+    let output = admin(path, None, &["ballots", "import", late.to_str().unwrap()]);
+    assert!(!output.status.success());
+    assert!(String::from_utf8_lossy(&output.stderr).contains("--force"));
+    succeed(admin(path, Some(&url), &["verify"]));
+
+    // Ballots slipped in after the draw break the cycle
+    rusqlite::Connection::open(&database)
+        .unwrap()
+        .execute_batch(
+            "INSERT INTO elector(elecId, elecIp, elecVoted) VALUES(100, '192.0.2.1', 1);
+            INSERT INTO ranking VALUES(100, 1, 3, 3), (100, 2, 2, 2), (100, 3, 1, 1);",
+        )
+        .unwrap();
+    let output = admin(path, Some(&url), &["verify"]);
+    assert!(!output.status.success());
+    assert!(String::from_utf8_lossy(&output.stderr).contains("does not match the ballots"));
+}
@@ -0,0 +1,280 @@
+//! Client of the REST interface of rcvs-web. Requests are sent with awc, so
+//! the client must be created and awaited from within an Actix system.
+
+use std::{error, fmt};
+
+use awc::http::{header, Method, StatusCode};
+use serde::de::DeserializeOwned;
+
+pub use rcvs_web_types::*;
+
+#[derive(Debug)]
+pub enum Error {
+    /// The request could not be sent or its response could not be read
+    Request(String),
+    /// The server answered with another status, and this message
+    Status(u16, String),
+    /// The response body does not have the expected type
+    Decode(String),
+}
+
+impl error::Error for Error {}
+
+impl fmt::Display for Error {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Self::Request(what) => write!(f, "Request failed: {}", what),
+            Self::Status(status, message) => write!(f, "{}: {}", status, message),
+            Self::Decode(what) => write!(f, "Unexpected response: {}", what),
+        }
+    }
+}
+
+/// Sends a request and checks the status of its response, whose body is
+/// returned
+async fn send(request: awc::SendClientRequest, expected: &[StatusCode]) -> Result<Vec<u8>, Error> {
+    let mut response = request
+        .await
+        .map_err(|what| Error::Request(what.to_string()))?;
+    let body = response
+        .body()
+        .await
+        .map_err(|what| Error::Request(what.to_string()))?;
+    if expected.contains(&response.status()) {
+        Ok(body.to_vec())
+    } else {
+        Err(Error::Status(
+            response.status().as_u16(),
+            String::from_utf8_lossy(&body).to_string(),
+        ))
+    }
+}
+
+fn decode<T: DeserializeOwned>(body: &[u8]) -> Result<T, Error> {
+    serde_json::from_slice(body).map_err(|what| Error::Decode(what.to_string()))
+}
+
+/// Client of one server, acting as one elector. The administration functions
+/// are only allowed from the loopback interface of the server, as sessions
+/// are only opened by browsers.
+pub struct Client {
+    base_url: String,
+    client: awc::Client,
+    credential: Option<String>,
+    secret: Option<String>,
+    language: Option<String>,
+}
+
+impl Client {
+    /// `base_url` is the address of the server, without the `/api` prefix,
+    /// e.g. `http://localhost:8080`
+    pub fn new(base_url: &str) -> Self {
+        Self {
+            base_url: base_url.trim_end_matches('/').to_string(),
+            client: awc::Client::default(),
+            credential: None,
+            secret: None,
+            language: None,
+        }
+    }
+
+    /// Credential issued from the voter roll, if the election has one
+    pub fn with_credential(mut self, credential: &str) -> Self {
+        self.credential = Some(credential.to_string());
+        self
+    }
+
+    /// Language of the error messages
+    pub fn with_language(mut self, language: &str) -> Self {
+        self.language = Some(language.to_string());
+        self
+    }
+
+    /// Ballot secret of an anonymous election, needed to modify or delete the
+    /// ballot. It is kept by `submit_ballot` when the server issues it.
+    pub fn set_secret(&mut self, secret: Option<String>) {
+        self.secret = secret;
+    }
+
+    pub fn get_secret(&self) -> Option<&str> {
+        self.secret.as_deref()
+    }
+
+    fn request(&self, method: Method, path: &str) -> awc::ClientRequest {
+        let mut request = self
+            .client
+            .request(method, format!("{}/api{}", self.base_url, path));
+        if let Some(credential) = &self.credential {
+            request = request.set_header(CREDENTIAL_HEADER, credential.as_str());
+        }
+        if let Some(secret) = &self.secret {
+            request = request.set_header(BALLOT_SECRET_HEADER, secret.as_str());
+        }
+        if let Some(language) = &self.language {
+            request = request.set_header(header::ACCEPT_LANGUAGE, language.as_str());
+        }
+        request
+    }
+
+    /// Alternatives of the election and ballot of the elector
+    pub async fn get_info(&self) -> Result<ElectionData, Error> {
+        let body = send(self.request(Method::GET, "/").send(), &[StatusCode::OK]).await?;
+        decode(&body)
+    }
+
+    /// Casts the ballot, or replaces the former one. Returns the secret issued
+    /// for the first ballot of an anonymous election.
+    pub async fn submit_ballot(&mut self, ballot: &[BallotRow]) -> Result<Option<String>, Error> {
+        let body = send(
+            self.request(Method::POST, "/ballot").send_json(&ballot),
+            &[StatusCode::CREATED, StatusCode::NO_CONTENT],
+        )
+        .await?;
+        if body.is_empty() {
+            return Ok(None);
+        }
+        let secret: SecretData = decode(&body)?;
+        self.secret = Some(secret.secret.to_string());
+        Ok(Some(secret.secret))
+    }
+
+    pub async fn delete_ballot(&self) -> Result<(), Error> {
+        send(
+            self.request(Method::DELETE, "/ballot").send(),
+            &[StatusCode::NO_CONTENT],
+        )
+        .await?;
+        Ok(())
+    }
+
+    /// Final result, or the result if the election closed now
+    pub async fn get_result(&self) -> Result<ResultData, Error> {
+        let body = send(
+            self.request(Method::GET, "/result").send(),
+            &[StatusCode::OK],
+        )
+        .await?;
+        decode(&body)
+    }
+
+    /// Voter roll, with who already voted
+    pub async fn get_roll(&self) -> Result<Vec<EnrolledElector>, Error> {
+        let body = send(self.request(Method::GET, "/roll").send(), &[StatusCode::OK]).await?;
+        decode(&body)
+    }
+
+    /// Enrolls the voters of a CSV roll. Returns the credential of each one.
+    pub async fn import_roll(&self, csv: &str) -> Result<Vec<IssuedCredential>, Error> {
+        let body = send(
+            self.request(Method::POST, "/roll")
+                .content_type("text/csv")
+                .send_body(csv.to_string()),
+            &[StatusCode::OK],
+        )
+        .await?;
+        decode(&body)
+    }
+
+    /// Renames the election
+    pub async fn set_title(&self, title: &str) -> Result<(), Error> {
+        send(
+            self.request(Method::PUT, "/title").send_json(&TitleData {
+                title: title.to_string(),
+            }),
+            &[StatusCode::NO_CONTENT],
+        )
+        .await?;
+        Ok(())
+    }
+
+    /// Adds an alternative. Returns it with its identifier.
+    pub async fn add_alternative(
+        &self,
+        alternative: &AlternativeEdit,
+    ) -> Result<AlternativeData, Error> {
+        let body = send(
+            self.request(Method::POST, "/alternatives")
+                .send_json(alternative),
+            &[StatusCode::CREATED],
+        )
+        .await?;
+        decode(&body)
+    }
+
+    /// Renames an alternative and replaces its description
+    pub async fn edit_alternative(
+        &self,
+        id: i64,
+        alternative: &AlternativeEdit,
+    ) -> Result<(), Error> {
+        send(
+            self.request(Method::PUT, &format!("/alternative/{}", id))
+                .send_json(alternative),
+            &[StatusCode::NO_CONTENT],
+        )
+        .await?;
+        Ok(())
+    }
+
+    /// Removes an alternative along with the ranks it was given
+    pub async fn remove_alternative(&self, id: i64) -> Result<(), Error> {
+        send(
+            self.request(Method::DELETE, &format!("/alternative/{}", id))
+                .send(),
+            &[StatusCode::NO_CONTENT],
+        )
+        .await?;
+        Ok(())
+    }
+
+    /// Number of ballots, in total and per alternative
+    pub async fn get_turnout(&self) -> Result<TurnoutData, Error> {
+        let body = send(
+            self.request(Method::GET, "/turnout").send(),
+            &[StatusCode::OK],
+        )
+        .await?;
+        decode(&body)
+    }
+
+    /// Rows of every ballot, as CSV
+    pub async fn export_ballots(&self) -> Result<String, Error> {
+        let body = send(
+            self.request(Method::GET, "/export/ballots").send(),
+            &[StatusCode::OK],
+        )
+        .await?;
+        String::from_utf8(body).map_err(|what| Error::Decode(what.to_string()))
+    }
+
+    /// Audit log, oldest entry first
+    pub async fn get_audit(&self) -> Result<Vec<AuditEntry>, Error> {
+        let body = send(
+            self.request(Method::GET, "/audit").send(),
+            &[StatusCode::OK],
+        )
+        .await?;
+        decode(&body)
+    }
+
+    /// Opens the election. Fails with status 409 if it is already open.
+    pub async fn open(&self) -> Result<(), Error> {
+        send(
+            self.request(Method::POST, "/open").send(),
+            &[StatusCode::NO_CONTENT],
+        )
+        .await?;
+        Ok(())
+    }
+
+    /// Closes the election and draws the winners. Fails with status 409 if
+    /// it is already closed, rather than drawing other winners.
+    pub async fn close(&self) -> Result<(), Error> {
+        send(
+            self.request(Method::POST, "/close").send(),
+            &[StatusCode::NO_CONTENT],
+        )
+        .await?;
+        Ok(())
+    }
+}
@@ -0,0 +1,1692 @@
+use std::fs;
+use std::io::{self, BufRead, BufReader, Write};
+use std::net::{TcpListener, TcpStream};
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+
+use actix_web::{test, web, App, HttpMessage, HttpRequest, HttpResponse};
+use hmac::{Hmac, Mac, NewMac};
+use jsonschema::{Draft, JSONSchema};
+use rcvs_web_client::{AlternativeEdit, BallotRow, Client, Error, ResultData, StrategyData};
+use serde_json::{json, Value};
+use sha2::{Digest, Sha384};
+use tempfile::TempDir;
+
+/// Server running in-process on a free port, with its data base in a
+/// directory removed when it is dropped
+struct TestServer {
+    server: test::TestServer,
+    directory: TempDir,
+    https: bool,
+}
+
+impl TestServer {
+    fn start(anonymous: bool) -> Self {
+        Self::start_with(json!({ "anonymous": anonymous }))
+    }
+
+    /// Starts a server whose configuration has these entries besides the
+    /// title and alternatives
+    fn start_with(options: Value) -> Self {
+        let directory = tempfile::tempdir().expect("Failed to create directory");
+        let mut election = json!({
+            "title": "Lunch",
+            "alternatives": [
+                { "id": "Pizza", "description": "With *olives*", "icon": "" },
+                { "id": "Sushi", "description": "", "icon": "" },
+                { "id": "Salad", "description": "", "icon": "" },
+            ],
+        });
+        if let (Some(election), Value::Object(options)) = (election.as_object_mut(), options) {
+            election.extend(options);
+        }
+        let config = directory.path().join("election.json");
+        fs::write(&config, election.to_string()).expect("Failed to write configuration");
+        Self::serve(directory)
+    }
+
+    fn serve(directory: TempDir) -> Self {
+        let path = |file: &str| {
+            directory
+                .path()
+                .join(file)
+                .to_str()
+                .expect("Path is UTF-8")
+                .to_string()
+        };
+        let app = rcvs_web::Server::new(&path("election.json"), &path("model.db"))
+            .expect("Failed to start server");
+        app.schedule_reminders();
+        let tls = app
+            .tls()
+            .map(|tls| tls.load().expect("Failed to load certificate"));
+        let https = tls.is_some();
+        let factory = move || {
+            App::new()
+                .wrap(rcvs_web::CatchPanic)
+                .wrap(app.hsts())
+                .wrap(rcvs_web::RequestMetrics)
+                .wrap(rcvs_web::RequestTracing)
+                .configure(|config| app.configure(config))
+        };
+        let server = match tls {
+            Some(tls) => test::start_with(test::config().rustls(tls), factory),
+            None => test::start(factory),
+        };
+        Self {
+            server: server,
+            directory: directory,
+            https: https,
+        }
+    }
+
+    /// Stops the server and starts another on the same configuration and
+    /// data base
+    fn restart(self) -> Self {
+        let Self {
+            server, directory, ..
+        } = self;
+        std::mem::drop(server);
+        Self::serve(directory)
+    }
+
+    fn url(&self) -> String {
+        if self.https {
+            // The test certificate is issued to localhost
+            format!("https://localhost:{}", self.server.addr().port())
+        } else {
+            format!("http://{}", self.server.addr())
+        }
+    }
+
+    fn client(&self) -> Client {
+        Client::new(&self.url())
+    }
+
+    async fn get_json(&self, path: &str) -> Value {
+        awc::Client::default()
+            .get(format!("{}{}", self.url(), path))
+            .send()
+            .await
+            .expect("Request failed")
+            .json()
+            .await
+            .expect("Response is not JSON")
+    }
+}
+
+/// SMTP server on a free port, keeping every message it is sent
+struct MailSink {
+    port: u16,
+    messages: Arc<Mutex<Vec<String>>>,
+}
+
+impl MailSink {
+    fn start() -> Self {
+        let listener = TcpListener::bind("127.0.0.1:0").expect("Failed to bind");
+        let port = listener.local_addr().unwrap().port();
+        let messages = Arc::new(Mutex::new(Vec::new()));
+        let received = messages.clone();
+        std::thread::spawn(move || {
+            for stream in listener.incoming().flatten() {
+                let received = received.clone();
+                std::thread::spawn(move || serve_smtp(stream, &received));
+            }
+        });
+        Self {
+            port: port,
+            messages: messages,
+        }
+    }
+
+    /// Waits for the given number of messages, and returns all of them
+    async fn wait_for(&self, count: usize) -> Vec<String> {
+        for _ in 0..100 {
+            if self.messages.lock().unwrap().len() >= count {
+                break;
+            }
+            actix_rt::time::delay_for(Duration::from_millis(50)).await;
+        }
+        self.messages.lock().unwrap().clone()
+    }
+}
+
+/// Decodes a quoted-printable message, headers included since they are
+/// plain ASCII anyway
+fn unquote(message: &str) -> String {
+    let message = message.replace("=\r\n", "");
+    let bytes = message.as_bytes();
+    let mut decoded = Vec::new();
+    let mut i = 0;
+    while i < bytes.len() {
+        let escaped = bytes
+            .get(i + 1..i + 3)
+            .and_then(|hex| std::str::from_utf8(hex).ok())
+            .and_then(|hex| u8::from_str_radix(hex, 16).ok());
+        match escaped {
+            Some(byte) if bytes[i] == b'=' => {
+                decoded.push(byte);
+                i += 3;
+            }
+            _ => {
+                decoded.push(bytes[i]);
+                i += 1;
+            }
+        }
+    }
+    String::from_utf8_lossy(&decoded).into_owned()
+}
+
+/// Accepts every command of one SMTP session
+fn serve_smtp(stream: TcpStream, messages: &Mutex<Vec<String>>) -> io::Result<()> {
+    let mut reader = BufReader::new(stream.try_clone()?);
+    let mut writer = stream;
+    writer.write_all(b"220 localhost\r\n")?;
+    let mut line = String::new();
+    while reader.read_line(&mut line)? > 0 {
+        let command = line.trim_end().to_ascii_uppercase();
+        if command.starts_with("DATA") {
+            writer.write_all(b"354 Go ahead\r\n")?;
+            let mut message = String::new();
+            let mut data = String::new();
+            while reader.read_line(&mut data)? > 0 && data != ".\r\n" {
+                message.push_str(&data);
+                data.clear();
+            }
+            messages.lock().unwrap().push(unquote(&message));
+            writer.write_all(b"250 Queued\r\n")?;
+        } else if command.starts_with("QUIT") {
+            return writer.write_all(b"221 Bye\r\n");
+        } else {
+            writer.write_all(b"250 OK\r\n")?;
+        }
+        line.clear();
+    }
+    Ok(())
+}
+
+fn ballot(ranks: &[(i64, u64)]) -> Vec<BallotRow> {
+    ranks
+        .iter()
+        .map(|&(alternative, rank)| BallotRow {
+            elector: None,
+            alternative: alternative as usize,
+            min: rank,
+            max: rank,
+        })
+        .collect()
+}
+
+/// Content of the `div` of a page with the given identifier, trimmed
+fn inner_text<'a>(html: &'a str, id: &str) -> &'a str {
+    let start = html
+        .find(&format!("<div id=\"{}\"", id))
+        .unwrap_or_else(|| panic!("No {} in the page", id));
+    let content = &html[start..];
+    let content = &content[content.find('>').unwrap() + 1..];
+    content[..content.find("</div>").unwrap()].trim()
+}
+
+/// Translates the OpenAPI `nullable` keyword, which JSON Schema ignores
+fn to_json_schema(schema: &mut Value) {
+    match schema {
+        Value::Object(object) => {
+            object.values_mut().for_each(to_json_schema);
+            if object.remove("nullable") == Some(Value::Bool(true)) {
+                *schema = json!({ "anyOf": [schema.clone(), { "type": "null" }] });
+            }
+        }
+        Value::Array(array) => array.iter_mut().for_each(to_json_schema),
+        _ => (),
+    }
+}
+
+/// Checks a response body against a schema of the OpenAPI document
+fn assert_follows(document: &Value, schema: &str, instance: &Value) {
+    let mut schemas = document["components"]["schemas"].clone();
+    to_json_schema(&mut schemas);
+    let root = json!({
+        "allOf": [{ "$ref": format!("#/components/schemas/{}", schema) }],
+        "components": { "schemas": schemas },
+    });
+    let compiled = JSONSchema::options()
+        .with_draft(Draft::Draft7)
+        .compile(&root)
+        .expect("Schema is valid");
+    let errors: Vec<String> = match compiled.validate(instance) {
+        Ok(()) => return,
+        Err(errors) => errors.map(|e| e.to_string()).collect(),
+    };
+    panic!("{} does not follow {}: {:?}", instance, schema, errors);
+}
+
+#[actix_rt::test]
+async fn elector_casts_and_withdraws_a_ballot() {
+    let server = TestServer::start(false);
+    let mut client = server.client();
+
+    let info = client.get_info().await.expect("Failed to get info");
+    assert_eq!(info.title.as_deref(), Some("Lunch"));
+    assert_eq!(info.alternatives.len(), 3);
+    assert!(info.ballot.is_empty());
+    assert_eq!(
+        info.alternatives[0].description_html.trim(),
+        "<p>With <em>olives</em></p>"
+    );
+
+    let ids: Vec<i64> = info.alternatives.iter().map(|a| a.id).collect();
+    let secret = client
+        .submit_ballot(&ballot(&[(ids[0], 2), (ids[1], 1)]))
+        .await
+        .expect("Failed to submit ballot");
+    assert_eq!(secret, None);
+    assert_eq!(client.get_info().await.unwrap().ballot.len(), 2);
+
+    client
+        .delete_ballot()
+        .await
+        .expect("Failed to delete ballot");
+    assert!(client.get_info().await.unwrap().ballot.is_empty());
+    match client.delete_ballot().await {
+        Err(Error::Status(404, _)) => (),
+        other => panic!("Deleted a missing ballot: {:?}", other),
+    }
+}
+
+#[actix_rt::test]
+async fn malformed_ballot_is_refused_in_the_requested_language() {
+    let server = TestServer::start(false);
+    let mut client = server.client().with_language("fr");
+    let id = client.get_info().await.unwrap().alternatives[0].id;
+
+    match client.submit_ballot(&ballot(&[(id, 1), (id, 2)])).await {
+        Err(Error::Status(400, message)) => assert!(message.contains("apparaît deux fois")),
+        other => panic!("Accepted a duplicate alternative: {:?}", other),
+    }
+}
+
+#[actix_rt::test]
+async fn form_ballot_is_read_back_through_the_api() {
+    let server = TestServer::start(false);
+    let client = server.client();
+    let ids: Vec<i64> = client
+        .get_info()
+        .await
+        .unwrap()
+        .alternatives
+        .iter()
+        .map(|a| a.id)
+        .collect();
+    let post = |form: Vec<(String, &'static str)>| {
+        awc::Client::default()
+            .post(format!("{}/vote", server.url()))
+            .header("Accept-Language", "en")
+            .send_form(&form)
+    };
+
+    let mut page = post(vec![
+        (format!("{}-low", ids[0]), "3"),
+        (format!("{}-high", ids[0]), "1"),
+    ])
+    .await
+    .unwrap();
+    assert_eq!(page.status(), 400);
+    let html = String::from_utf8(page.body().await.unwrap().to_vec()).unwrap();
+    assert_ne!(inner_text(&html, "ballot-errors"), "&nbsp;");
+    assert_eq!(inner_text(&html, "send-status"), "&nbsp;");
+    assert!(client.get_info().await.unwrap().ballot.is_empty());
+
+    let mut page = post(vec![
+        (format!("{}-low", ids[1]), "2"),
+        (format!("{}-high", ids[1]), "3"),
+        (format!("{}-low", ids[2]), "1"),
+        (format!("{}-high", ids[2]), "1"),
+        (format!("{}-low", ids[0]), ""),
+        (format!("{}-high", ids[0]), ""),
+    ])
+    .await
+    .unwrap();
+    assert_eq!(page.status(), 200);
+    let html = String::from_utf8(page.body().await.unwrap().to_vec()).unwrap();
+    assert_eq!(inner_text(&html, "ballot-errors"), "&nbsp;");
+    assert_eq!(inner_text(&html, "send-status"), "Successfully sent ballot");
+
+    let mut rows: Vec<(i64, u64, u64)> = client
+        .get_info()
+        .await
+        .unwrap()
+        .ballot
+        .iter()
+        .map(|row| (row.alternative as i64, row.min, row.max))
+        .collect();
+    rows.sort_unstable();
+    let mut expected = vec![(ids[1], 2, 3), (ids[2], 1, 1)];
+    expected.sort_unstable();
+    assert_eq!(rows, expected);
+}
+
+#[actix_rt::test]
+async fn theme_is_rendered_in_every_page() {
+    let stylesheet = tempfile::NamedTempFile::new().unwrap();
+    fs::write(stylesheet.path(), "h1 { font-variant: small-caps; }").unwrap();
+    let server = TestServer::start_with(json!({
+        "theme": {
+            "logo": "https://example.org/logo.png",
+            "palette": { "background": "#fdfdf8", "link": "rgb(11, 83, 148)" },
+            "stylesheet": stylesheet.path(),
+            "intro": "Welcome to the board election.\n\nVoting closes on Friday.",
+            "footer": [{ "label": "Legal notice", "url": "https://example.org/legal" }],
+        },
+    }));
+
+    for path in &["/", "/vote", "/result"] {
+        let mut page = awc::Client::default()
+            .get(format!("{}{}", server.url(), path))
+            .send()
+            .await
+            .unwrap();
+        assert_eq!(page.status(), 200, "{}", path);
+        let html = String::from_utf8(page.body().await.unwrap().to_vec()).unwrap();
+        for expected in &[
+            "<img id=\"logo\" src=\"https://example.org/logo.png\"",
+            "body { background-color: #fdfdf8; }",
+            "a { color: rgb(11, 83, 148); }",
+            "h1 { font-variant: small-caps; }",
+            "<a href=\"https://example.org/legal\">Legal notice</a>",
+        ] {
+            assert!(html.contains(expected), "{} lacks {}", path, expected);
+        }
+        if *path == "/" {
+            assert!(html.contains("Welcome to the board election."));
+            assert!(html.contains("Voting closes on Friday."));
+        }
+    }
+}
+
+#[actix_rt::test]
+async fn uploaded_icon_is_reencoded_next_to_the_data_base() {
+    // One red pixel
+    const GIF: &[u8] = b"GIF89a\x01\x00\x01\x00\x80\x00\x00\xff\x00\x00\x00\x00\x00\x21\xf9\x04\x01\x00\x00\x00\x00\x2c\x00\x00\x00\x00\x01\x00\x01\x00\x00\x02\x02\x44\x01\x00\x3b";
+    const PNG_SIGNATURE: &[u8] = b"\x89PNG\r\n\x1a\n";
+    let server = TestServer::start(false);
+    let client = server.client();
+    let id = client.get_info().await.unwrap().alternatives[0].id;
+
+    let response: Value = awc::Client::default()
+        .put(format!("{}/api/alternative/{}/icon", server.url(), id))
+        .send_body(GIF)
+        .await
+        .unwrap()
+        .json()
+        .await
+        .unwrap();
+    let icon = response["icon"].as_str().expect("No icon path").to_string();
+    assert!(icon.starts_with("/assets/icons/") && icon.ends_with(".png"));
+    let stored = fs::read(server.directory.path().join(&icon[1..])).expect("Icon not stored");
+    assert!(stored.starts_with(PNG_SIGNATURE));
+
+    let mut served = awc::Client::default()
+        .get(format!("{}{}", server.url(), icon))
+        .send()
+        .await
+        .unwrap();
+    assert_eq!(served.status(), 200);
+    assert_eq!(served.body().await.unwrap().to_vec(), stored);
+    let info = client.get_info().await.unwrap();
+    assert_eq!(info.alternatives[0].icon, icon);
+
+    match awc::Client::default()
+        .put(format!("{}/api/alternative/{}/icon", server.url(), id))
+        .send_body("<svg onload=\"alert(1)\"/>")
+        .await
+    {
+        Ok(response) => assert_eq!(response.status(), 415),
+        Err(what) => panic!("Upload failed: {}", what),
+    }
+}
+
+#[actix_rt::test]
+async fn descriptions_are_sanitized() {
+    let server = TestServer::start(false);
+    let mut client = server.client();
+    let source = "*Fresh* and [cheap](https://example.org/menu)\n\n\
+                  * rice\n* fish\n\n\
+                  <script>alert('script')</script>\n\n\
+                  <img src=\"x.png\" onerror=\"alert('img')\">\n\n\
+                  [click](javascript:alert('link'))\n\n\
+                  <iframe src=\"https://example.org/frame\"></iframe>";
+    let added = client
+        .add_alternative(&edit("Poke", source))
+        .await
+        .expect("Failed to add alternative");
+    let html = added.description_html;
+    assert_eq!(added.description, source);
+
+    assert!(html.contains("<em>Fresh</em>"), "{}", html);
+    assert!(
+        html.contains(
+            "<a href=\"https://example.org/menu\" rel=\"noopener noreferrer nofollow\">cheap</a>"
+        ),
+        "{}",
+        html
+    );
+    assert!(
+        html.contains("<ul>\n<li>rice</li>\n<li>fish</li>\n</ul>"),
+        "{}",
+        html
+    );
+    assert!(html.contains("click"), "{}", html);
+    for removed in &[
+        "<script",
+        "alert",
+        "<img",
+        "onerror",
+        "javascript:",
+        "<iframe",
+    ] {
+        assert!(!html.contains(removed), "{} left in {}", removed, html);
+    }
+
+    // The pages render the same sanitized HTML
+    let info = client.get_info().await.unwrap();
+    let alternative = info.alternatives.iter().find(|a| a.name == "Poke").unwrap();
+    assert_eq!(alternative.description_html, html);
+    client
+        .submit_ballot(&ballot(&[(alternative.id, 1)]))
+        .await
+        .unwrap();
+    let mut page = awc::Client::default()
+        .get(format!("{}/vote", server.url()))
+        .send()
+        .await
+        .unwrap();
+    let page = String::from_utf8(page.body().await.unwrap().to_vec()).unwrap();
+    assert!(page.contains("<em>Fresh</em>"));
+    // The source is only found escaped in the election data
+    for removed in &[
+        "<script>alert",
+        "<img src=\"x.png\"",
+        "href=\"javascript:",
+        "<iframe",
+    ] {
+        assert!(!page.contains(removed), "{} left in the page", removed);
+    }
+}
+
+#[actix_rt::test]
+async fn anonymous_ballot_needs_its_secret() {
+    let server = TestServer::start(true);
+    let mut client = server.client();
+    let ids: Vec<i64> = client
+        .get_info()
+        .await
+        .unwrap()
+        .alternatives
+        .iter()
+        .map(|a| a.id)
+        .collect();
+
+    let secret = client
+        .submit_ballot(&ballot(&[(ids[0], 1)]))
+        .await
+        .expect("Failed to submit ballot")
+        .expect("No secret issued");
+    assert_eq!(client.get_secret(), Some(secret.as_str()));
+
+    let mut stranger = server.client();
+    match stranger.submit_ballot(&ballot(&[(ids[1], 1)])).await {
+        Err(Error::Status(403, _)) => (),
+        other => panic!("Replaced a ballot without its secret: {:?}", other),
+    }
+
+    stranger.set_secret(Some(secret));
+    let secret = stranger
+        .submit_ballot(&ballot(&[(ids[1], 1)]))
+        .await
+        .expect("Failed to replace ballot");
+    assert_eq!(secret, None);
+}
+
+#[actix_rt::test]
+async fn anonymous_mode_cannot_change_after_creation() {
+    let server = TestServer::start(true);
+    let TestServer {
+        server, directory, ..
+    } = server;
+    std::mem::drop(server);
+
+    let path = |file: &str| directory.path().join(file).to_str().unwrap().to_string();
+    let config = path("election.json");
+    let mut election: Value = serde_json::from_str(&fs::read_to_string(&config).unwrap()).unwrap();
+    election["anonymous"] = json!(false);
+    fs::write(&config, election.to_string()).unwrap();
+    match rcvs_web::Server::new(&config, &path("model.db")) {
+        Err(what) => assert!(what.to_string().contains("anonymous"), "{}", what),
+        Ok(_) => panic!("Started with another mode than the data base's"),
+    }
+
+    election["anonymous"] = json!(true);
+    fs::write(&config, election.to_string()).unwrap();
+    assert!(rcvs_web::Server::new(&config, &path("model.db")).is_ok());
+}
+
+#[actix_rt::test]
+async fn closing_draws_the_condorcet_winner() {
+    let server = TestServer::start(false);
+    let mut client = server.client();
+    let ids: Vec<i64> = client
+        .get_info()
+        .await
+        .unwrap()
+        .alternatives
+        .iter()
+        .map(|a| a.id)
+        .collect();
+    client
+        .submit_ballot(&ballot(&[(ids[1], 3), (ids[0], 2), (ids[2], 1)]))
+        .await
+        .unwrap();
+
+    let result = client.get_result().await.expect("Failed to get result");
+    assert_eq!(result.winner, None);
+    assert_eq!(result.arrows.len(), 3);
+
+    client.close().await.expect("Failed to close");
+    let result = client.get_result().await.unwrap();
+    assert_eq!(result.winner, Some(ids[1] as usize));
+    match client.close().await {
+        Err(Error::Status(409, _)) => (),
+        other => panic!("Closed a closed election: {:?}", other),
+    }
+    assert_eq!(result.get_winner_names(), vec!["Sushi".to_string()]);
+    match client.submit_ballot(&ballot(&[(ids[0], 1)])).await {
+        Err(Error::Status(403, _)) => (),
+        other => panic!("Voted in a closed election: {:?}", other),
+    }
+
+    match client.open().await {
+        Err(Error::Status(409, _)) => (),
+        other => panic!("Reopened a final election: {:?}", other),
+    }
+    assert_eq!(client.get_result().await.unwrap().draws, 1);
+}
+
+#[actix_rt::test]
+async fn closing_fills_every_seat_once() {
+    for &(seats, elected) in &[(2, 2), (5, 3)] {
+        let server = TestServer::start_with(json!({ "seats": seats }));
+        let mut client = server.client();
+        let ids: Vec<i64> = client
+            .get_info()
+            .await
+            .unwrap()
+            .alternatives
+            .iter()
+            .map(|a| a.id)
+            .collect();
+        client
+            .submit_ballot(&ballot(&[(ids[1], 3), (ids[0], 2), (ids[2], 1)]))
+            .await
+            .unwrap();
+
+        client.close().await.expect("Failed to close");
+        let result = client.get_result().await.unwrap();
+        assert_eq!(result.seats, seats);
+        assert_eq!(result.winners.len(), elected, "{} seats", seats);
+        let mut distinct = result.winners.clone();
+        distinct.sort_unstable();
+        distinct.dedup();
+        assert_eq!(distinct.len(), elected, "{:?}", result.winners);
+        assert_eq!(result.winner, Some(result.winners[0]));
+        assert_eq!(
+            result.get_winner_names()[..2],
+            ["Sushi".to_string(), "Pizza".to_string()]
+        );
+    }
+}
+
+/// Closes an election where Ann prefers Pizza and two voters Sushi, all of
+/// them ranking Salad last, and returns the result
+async fn weighted_result(ann: &str) -> ResultData {
+    let server = TestServer::start_with(json!({ "voter_roll": true }));
+    let client = server.client();
+    let roll = format!(
+        "name,email,weight\nAnn,ann@example.org,{}\nBob,bob@example.org,1\nCat,cat@example.org,1\n",
+        ann
+    );
+    let credentials = client.import_roll(&roll).await.expect("Failed to import");
+    let ids: Vec<i64> = client
+        .get_result()
+        .await
+        .unwrap()
+        .alternatives
+        .iter()
+        .map(|a| a.id)
+        .collect();
+    for issued in &credentials {
+        let ranks = if issued.name == "Ann" {
+            [(ids[0], 3), (ids[1], 2), (ids[2], 1)]
+        } else {
+            [(ids[1], 3), (ids[0], 2), (ids[2], 1)]
+        };
+        let mut voter = server.client().with_credential(&issued.credential);
+        voter.submit_ballot(&ballot(&ranks)).await.unwrap();
+    }
+
+    let turnout = client.get_turnout().await.unwrap();
+    assert_eq!(turnout.weight, ann.parse::<f64>().unwrap() + 2.0);
+    client.close().await.unwrap();
+    client.get_result().await.unwrap()
+}
+
+#[actix_rt::test]
+async fn weights_change_the_winner() {
+    let winner = |result: &ResultData| result.get_winner_names()[0].clone();
+    assert_eq!(winner(&weighted_result("1").await), "Sushi");
+    assert_eq!(winner(&weighted_result("2.5").await), "Pizza");
+
+    // Equal weights on both sides: neither Pizza nor Sushi wins their duel
+    let result = weighted_result("2").await;
+    let mut arrows: Vec<(String, String)> = result
+        .arrows
+        .iter()
+        .map(|a| {
+            (
+                result.get_name(a.from).unwrap(),
+                result.get_name(a.to).unwrap(),
+            )
+        })
+        .collect();
+    arrows.sort();
+    let expected = [("Pizza", "Salad"), ("Sushi", "Salad")];
+    let expected: Vec<(String, String)> = expected
+        .iter()
+        .map(|&(from, to)| (from.to_string(), to.to_string()))
+        .collect();
+    assert_eq!(arrows, expected);
+}
+
+#[actix_rt::test]
+async fn voter_roll_admits_enrolled_voters_only() {
+    let server = TestServer::start_with(json!({ "voter_roll": true }));
+    let client = server.client();
+    let roll = "name,email,group\nAnn,ann@example.org,staff\nBob,bob@example.org,\n";
+    let credentials = client.import_roll(roll).await.expect("Failed to import");
+    assert_eq!(credentials.len(), 2);
+    assert_ne!(credentials[0].credential, credentials[1].credential);
+
+    // Importing again updates the voters but keeps their credentials
+    let again = client
+        .import_roll("name,email\nAnne,ann@example.org\n")
+        .await
+        .unwrap();
+    assert_eq!(again[0].credential, credentials[0].credential);
+    let enrolled = client.get_roll().await.unwrap();
+    assert_eq!(enrolled.len(), 2);
+    assert!(enrolled.iter().any(|e| e.name == "Anne"));
+    assert!(enrolled.iter().all(|e| !e.voted));
+
+    let id = client.get_result().await.unwrap().alternatives[0].id;
+    let mut ann = server.client().with_credential(&credentials[0].credential);
+    ann.submit_ballot(&ballot(&[(id, 1)]))
+        .await
+        .expect("Enrolled voter could not vote");
+    assert_eq!(ann.get_info().await.unwrap().ballot.len(), 1);
+    let voted: Vec<bool> = client
+        .get_roll()
+        .await
+        .unwrap()
+        .iter()
+        .map(|e| e.voted)
+        .collect();
+    assert_eq!(voted.iter().filter(|&&v| v).count(), 1);
+
+    // The vote page logs the voter in from the credential in its link
+    let page = awc::Client::default()
+        .get(format!(
+            "{}/vote?credential={}",
+            server.url(),
+            credentials[1].credential
+        ))
+        .send()
+        .await
+        .unwrap();
+    assert_eq!(page.status(), 200);
+    let page = awc::Client::default()
+        .get(format!("{}/vote", server.url()))
+        .send()
+        .await
+        .unwrap();
+    assert_eq!(page.status(), 401);
+
+    let mut stranger = server.client();
+    match stranger.submit_ballot(&ballot(&[(id, 1)])).await {
+        Err(Error::Status(401, message)) => assert_eq!(message, "Missing voter credential"),
+        other => panic!("Voted without a credential: {:?}", other),
+    }
+    let mut impostor = server.client().with_credential("not-a-credential");
+    match impostor.submit_ballot(&ballot(&[(id, 1)])).await {
+        Err(Error::Status(403, message)) => assert_eq!(message, "Not on the voter roll"),
+        other => panic!("Voted with an unknown credential: {:?}", other),
+    }
+    assert_eq!(client.get_turnout().await.unwrap().ballots, 1);
+}
+
+#[actix_rt::test]
+async fn voters_are_mailed_and_reminded_once() {
+    let sink = MailSink::start();
+    let mut mail = json!({
+        "host": "127.0.0.1",
+        "port": sink.port,
+        "tls": false,
+        "from": "rcvs@example.org",
+        "base_url": "https://vote.example.org",
+        "templates": concat!(env!("CARGO_MANIFEST_DIR"), "/../mail"),
+    });
+    let server = TestServer::start_with(json!({ "voter_roll": true, "mail": mail }));
+    let client = server.client();
+    let roll = "name,email\nEve $LINK $TITLE,eve@example.org\nBob,bob@example.org\n";
+    let credentials = client.import_roll(roll).await.unwrap();
+
+    let response = awc::Client::default()
+        .post(format!("{}/api/mail/invitations", server.url()))
+        .send()
+        .await
+        .unwrap();
+    assert_eq!(response.status(), 200);
+    let messages = sink.wait_for(2).await;
+    assert_eq!(messages.len(), 2);
+    let eve = messages
+        .iter()
+        .find(|m| m.contains("To: eve@example.org"))
+        .expect("Eve was not invited");
+    assert!(
+        eve.contains("Subject: You are invited to vote in Lunch"),
+        "{}",
+        eve
+    );
+    assert!(eve.contains("Hello Eve $LINK $TITLE,"), "{}", eve);
+    let link = format!(
+        "https://vote.example.org/vote?credential={}",
+        credentials[0].credential
+    );
+    assert!(eve.contains(&link), "{}", eve);
+
+    // Bob votes; the reminder is then due and only Eve gets it
+    let id = client.get_result().await.unwrap().alternatives[0].id;
+    let mut bob = server.client().with_credential(&credentials[1].credential);
+    bob.submit_ballot(&ballot(&[(id, 1)])).await.unwrap();
+    let config = server.directory.path().join("election.json");
+    let mut election: Value = serde_json::from_str(&fs::read_to_string(&config).unwrap()).unwrap();
+    mail["reminder_at"] = json!(1);
+    election["mail"] = mail;
+    fs::write(&config, election.to_string()).unwrap();
+    let server = server.restart();
+    let messages = sink.wait_for(3).await;
+    assert_eq!(messages.len(), 3);
+    assert!(messages[2].contains("Subject: Reminder: Lunch closes soon"));
+    assert!(messages[2].contains("To: eve@example.org"));
+
+    // Restarting does not send the reminder again, once it is recorded after
+    // the SMTP session ends
+    actix_rt::time::delay_for(Duration::from_millis(500)).await;
+    server.restart();
+    actix_rt::time::delay_for(Duration::from_secs(1)).await;
+    assert_eq!(sink.messages.lock().unwrap().len(), 3);
+}
+
+#[actix_rt::test]
+async fn mail_templates_are_found_next_to_the_data_base() {
+    let sink = MailSink::start();
+    let server = TestServer::start_with(json!({
+        "voter_roll": true,
+        "mail": {
+            "host": "127.0.0.1",
+            "port": sink.port,
+            "tls": false,
+            "from": "rcvs@example.org",
+            "base_url": "https://vote.example.org",
+        },
+    }));
+    let templates = server.directory.path().join("mail");
+    fs::create_dir(&templates).unwrap();
+    fs::write(
+        templates.join("invitation.txt"),
+        "Ballot for $NAME\n\nVote at $LINK\n",
+    )
+    .unwrap();
+    let client = server.client();
+    client
+        .import_roll("name,email\nEve,eve@example.org\n")
+        .await
+        .unwrap();
+
+    let response = awc::Client::default()
+        .post(format!("{}/api/mail/invitations", server.url()))
+        .send()
+        .await
+        .unwrap();
+    assert_eq!(response.status(), 200);
+    let messages = sink.wait_for(1).await;
+    assert!(
+        messages[0].contains("Subject: Ballot for Eve"),
+        "{}",
+        messages[0]
+    );
+}
+
+#[actix_rt::test]
+async fn webhooks_are_signed_and_retried() {
+    // Refuses the first delivery, then keeps the signature and body of every
+    // delivery accepted
+    let received = Arc::new(Mutex::new(Vec::<(String, String)>::new()));
+    let attempts = Arc::new(AtomicUsize::new(0));
+    let receiver = {
+        let received = received.clone();
+        let attempts = attempts.clone();
+        test::start(move || {
+            let received = received.clone();
+            let attempts = attempts.clone();
+            App::new().route(
+                "/hook",
+                web::post().to(move |req: HttpRequest, body: String| {
+                    let signature = req
+                        .headers()
+                        .get("X-Rcvs-Signature")
+                        .and_then(|value| value.to_str().ok())
+                        .unwrap_or_default()
+                        .to_string();
+                    let first = attempts.fetch_add(1, Ordering::SeqCst) == 0;
+                    if !first {
+                        received.lock().unwrap().push((signature, body));
+                    }
+                    std::future::ready(if first {
+                        HttpResponse::ServiceUnavailable().finish()
+                    } else {
+                        HttpResponse::NoContent().finish()
+                    })
+                }),
+            )
+        })
+    };
+    let url = format!("http://{}/hook", receiver.addr());
+    let server = TestServer::start_with(json!({
+        "webhooks": [{ "url": url, "secret": "shared", "attempts": 3 }],
+        "ballot_milestones": [1],
+    }));
+    let mut client = server.client();
+    let id = client.get_info().await.unwrap().alternatives[0].id;
+    client.submit_ballot(&ballot(&[(id, 1)])).await.unwrap();
+
+    // The second attempt comes a second after the first
+    for _ in 0..100 {
+        if !received.lock().unwrap().is_empty() {
+            break;
+        }
+        actix_rt::time::delay_for(Duration::from_millis(50)).await;
+    }
+    let (signature, body) = received.lock().unwrap().pop().expect("Nothing delivered");
+    assert_eq!(attempts.load(Ordering::SeqCst), 2);
+    let mut mac = Hmac::<sha2::Sha256>::new_varkey(b"shared").unwrap();
+    mac.update(body.as_bytes());
+    assert_eq!(
+        signature,
+        format!("sha256={}", hex::encode(mac.finalize().into_bytes()))
+    );
+    let event: Value = serde_json::from_str(&body).unwrap();
+    assert_eq!(event["event"], "ballot_milestone");
+    assert_eq!(event["data"]["ballots"], 1);
+
+    // The attempt is logged once its response is read
+    let mut deliveries = server.get_json("/api/webhooks/deliveries").await;
+    for _ in 0..100 {
+        if deliveries.as_array().map_or(0, Vec::len) >= 2 {
+            break;
+        }
+        actix_rt::time::delay_for(Duration::from_millis(50)).await;
+        deliveries = server.get_json("/api/webhooks/deliveries").await;
+    }
+    let mut attempts: Vec<(u64, Value)> = deliveries
+        .as_array()
+        .unwrap()
+        .iter()
+        .map(|d| {
+            assert_eq!(d["url"], json!(url));
+            assert_eq!(d["event"], "ballot_milestone");
+            (d["attempt"].as_u64().unwrap(), d["status"].clone())
+        })
+        .collect();
+    attempts.sort_by_key(|(attempt, _)| *attempt);
+    assert_eq!(attempts, vec![(1, json!(503)), (2, json!(204))]);
+
+    // The milestone stays reached across restarts
+    let server = server.restart();
+    let mut client = server.client();
+    client.submit_ballot(&ballot(&[(id, 2)])).await.unwrap();
+    actix_rt::time::delay_for(Duration::from_millis(500)).await;
+    assert!(received.lock().unwrap().is_empty());
+    assert_eq!(
+        server.get_json("/api/webhooks/deliveries").await,
+        deliveries
+    );
+}
+
+#[actix_rt::test]
+async fn deleted_anonymous_ballots_are_not_counted() {
+    let server = TestServer::start(true);
+    let mut client = server.client();
+    let id = client.get_info().await.unwrap().alternatives[0].id;
+    client.submit_ballot(&ballot(&[(id, 1)])).await.unwrap();
+    client.delete_ballot().await.unwrap();
+
+    let response = awc::Client::default()
+        .get(format!("{}/metrics", server.url()))
+        .send()
+        .await
+        .unwrap()
+        .body()
+        .await
+        .unwrap();
+    let metrics = std::str::from_utf8(&response).unwrap();
+    assert!(metrics.contains("rcvs_ballots 0"), "{}", metrics);
+}
+
+#[actix_rt::test]
+async fn weights_are_bounded() {
+    let server = TestServer::start_with(json!({ "voter_roll": true }));
+    let client = server.client();
+    for weight in &["-1", "1000001", "NaN", "inf"] {
+        let roll = format!("name,email,weight\nAnn,ann@example.org,{}\n", weight);
+        match client.import_roll(&roll).await {
+            Err(Error::Status(400, _)) => (),
+            other => panic!("Imported a weight of {}: {:?}", weight, other),
+        }
+    }
+    assert!(client.get_roll().await.unwrap().is_empty());
+
+    // Enrolled voters are not known by address
+    let response = awc::Client::default()
+        .put(format!("{}/api/elector/192.0.2.1", server.url()))
+        .send_json(&json!({ "weight": 2 }))
+        .await
+        .unwrap();
+    assert_eq!(response.status(), 409);
+
+    let server = TestServer::start(false);
+    for (weight, status) in &[(json!(1e7), 400), (json!(-0.5), 400), (json!(0.0004), 204)] {
+        let response = awc::Client::default()
+            .put(format!("{}/api/elector/192.0.2.1", server.url()))
+            .send_json(&json!({ "weight": weight }))
+            .await
+            .unwrap();
+        assert_eq!(response.status(), *status, "{}", weight);
+    }
+}
+
+#[actix_rt::test]
+async fn reopening_keeps_count_of_the_draws() {
+    let server = TestServer::start_with(json!({ "allow_reopen": true }));
+    let client = server.client();
+
+    assert_eq!(client.get_result().await.unwrap().draws, 0);
+    client.close().await.unwrap();
+    client.open().await.expect("Failed to reopen");
+    match client.open().await {
+        Err(Error::Status(409, _)) => (),
+        other => panic!("Opened an open election: {:?}", other),
+    }
+    assert_eq!(client.get_result().await.unwrap().draws, 1);
+    client.close().await.unwrap();
+    let result = client.get_result().await.unwrap();
+    assert_eq!(result.draws, 2);
+
+    let server = server.restart();
+    let restored = server.client().get_result().await.unwrap();
+    assert_eq!(restored.draws, 2);
+    assert_eq!(restored.winners, result.winners);
+    match server.client().close().await {
+        Err(Error::Status(409, _)) => (),
+        other => panic!("Closed a restored election again: {:?}", other),
+    }
+}
+
+#[actix_rt::test]
+async fn admin_actions_need_post_from_the_same_origin() {
+    let server = TestServer::start(false);
+    let client = awc::Client::default();
+
+    let response = client
+        .get(format!("{}/api/close", server.url()))
+        .send()
+        .await
+        .unwrap();
+    assert!(!response.status().is_success());
+
+    let response = client
+        .post(format!("{}/api/close", server.url()))
+        .set_header("Origin", "http://elsewhere.example")
+        .send()
+        .await
+        .unwrap();
+    assert_eq!(response.status(), 403);
+    assert_eq!(server.get_json("/api/result").await["winners"], json!([]));
+
+    let response = client
+        .post(format!("{}/api/close", server.url()))
+        .set_header("Origin", server.url())
+        .send()
+        .await
+        .unwrap();
+    assert_eq!(response.status(), 204);
+}
+
+fn edit(name: &str, description: &str) -> AlternativeEdit {
+    AlternativeEdit {
+        name: name.to_string(),
+        description: description.to_string(),
+    }
+}
+
+#[actix_rt::test]
+async fn administrator_edits_the_election() {
+    let server = TestServer::start(false);
+    let mut client = server.client();
+
+    let added = client
+        .add_alternative(&edit("Tacos", "With *salsa*"))
+        .await
+        .expect("Failed to add alternative");
+    assert_eq!(added.description_html.trim(), "<p>With <em>salsa</em></p>");
+    match client.add_alternative(&edit("Pizza", "")).await {
+        Err(Error::Status(409, _)) => (),
+        other => panic!("Added a duplicate alternative: {:?}", other),
+    }
+    client
+        .edit_alternative(added.id, &edit("Burritos", ""))
+        .await
+        .expect("Failed to edit alternative");
+    client.set_title("Dinner").await.expect("Failed to rename");
+
+    let info = client.get_info().await.unwrap();
+    assert_eq!(info.title.as_deref(), Some("Dinner"));
+    assert_eq!(info.alternatives.len(), 4);
+    assert!(info.alternatives.iter().any(|a| a.name == "Burritos"));
+
+    client
+        .submit_ballot(&ballot(&[(added.id, 2), (info.alternatives[0].id, 1)]))
+        .await
+        .unwrap();
+    let turnout = client.get_turnout().await.expect("Failed to get turnout");
+    assert!(turnout.open);
+    assert_eq!((turnout.ballots, turnout.weight), (1, 1.0));
+    let csv = client.export_ballots().await.expect("Failed to export");
+    assert!(csv.starts_with("ballot,weight,alternative,min,max\n"));
+    assert!(csv.contains("1,1.0,Burritos,2,2\n"));
+
+    client
+        .remove_alternative(added.id)
+        .await
+        .expect("Failed to remove alternative");
+    match client.remove_alternative(added.id).await {
+        Err(Error::Status(404, _)) => (),
+        other => panic!("Removed a missing alternative: {:?}", other),
+    }
+    let turnout = client.get_turnout().await.unwrap();
+    assert_eq!(turnout.ranked.len(), 3);
+    assert!(turnout
+        .ranked
+        .iter()
+        .all(|r| r.alternative != added.id as usize));
+
+    let dashboard = awc::Client::default()
+        .get(format!("{}/admin", server.url()))
+        .send()
+        .await
+        .unwrap();
+    assert_eq!(dashboard.status(), 200);
+
+    client.close().await.unwrap();
+    match client.set_title("Breakfast").await {
+        Err(Error::Status(409, _)) => (),
+        other => panic!("Renamed a closed election: {:?}", other),
+    }
+}
+
+#[actix_rt::test]
+async fn responses_carry_the_request_id() {
+    let server = TestServer::start(false);
+    let url = format!("{}/api/", server.url());
+
+    let response = awc::Client::default().get(&url).send().await.unwrap();
+    let id = response
+        .headers()
+        .get("X-Request-Id")
+        .expect("No request ID");
+    assert_eq!(id.len(), 16);
+
+    let response = awc::Client::default()
+        .get(&url)
+        .set_header("X-Request-Id", "from-proxy.42")
+        .send()
+        .await
+        .unwrap();
+    assert_eq!(
+        response.headers().get("X-Request-Id").unwrap(),
+        "from-proxy.42"
+    );
+    let response = awc::Client::default()
+        .get(&url)
+        .set_header("X-Request-Id", "forged\tline")
+        .send()
+        .await
+        .unwrap();
+    assert_ne!(
+        response.headers().get("X-Request-Id").unwrap(),
+        "forged\tline"
+    );
+}
+
+/// Client trusting the authority of the test certificate
+fn https_client() -> awc::Client {
+    let mut config = rustls::ClientConfig::new();
+    let authority = fs::read(tls_file("ca.pem")).expect("Failed to read authority");
+    config
+        .root_store
+        .add_pem_file(&mut &authority[..])
+        .expect("Invalid authority");
+    awc::Client::builder()
+        .connector(awc::Connector::new().rustls(Arc::new(config)).finish())
+        .finish()
+}
+
+fn tls_file(name: &str) -> String {
+    format!("{}/tests/tls/{}", env!("CARGO_MANIFEST_DIR"), name)
+}
+
+#[actix_rt::test]
+async fn https_is_served_with_hsts() {
+    let tls = json!({
+        "certificate": tls_file("cert.pem"),
+        "key": tls_file("key.pem"),
+        "address": "0.0.0.0:8443",
+        "hsts_max_age": 600,
+    });
+    let server = TestServer::start_with(json!({
+        "tls": tls,
+        // SHA-256 of "secret"
+        "admin": {
+            "password_sha256": "2bb80d537b1da3e38bd30361aa855686bde0eacd7162fef6a25fe97bf527a25b",
+        },
+    }));
+    let client = https_client();
+
+    let response = client
+        .get(format!("{}/api/", server.url()))
+        .send()
+        .await
+        .unwrap();
+    assert_eq!(response.status(), 200);
+    assert_eq!(
+        response.headers().get("Strict-Transport-Security").unwrap(),
+        "max-age=600"
+    );
+
+    let response = client
+        .post(format!("{}/admin/login", server.url()))
+        .send_form(&[("password", "secret")])
+        .await
+        .unwrap();
+    assert_eq!(response.status(), 303);
+    let cookie = response.cookie("rcvs-admin").unwrap();
+    assert_eq!(cookie.secure(), Some(true));
+
+    // The plain listener sends everything to HTTPS
+    let tls: rcvs_web::TlsConfig = serde_json::from_value(tls).unwrap();
+    let redirect =
+        test::start(move || App::new().configure(|config| tls.configure_redirect(config)));
+    let response = awc::Client::default()
+        .post(redirect.url("/vote?lang=fr"))
+        .header("Host", "vote.example:80")
+        .send()
+        .await
+        .unwrap();
+    assert_eq!(response.status(), 308);
+    assert_eq!(
+        response.headers().get("Location").unwrap(),
+        "https://vote.example:8443/vote?lang=fr"
+    );
+}
+
+#[actix_rt::test]
+async fn ballots_are_rate_limited() {
+    let server = TestServer::start_with(json!({
+        "rate_limit": {
+            "per_ip": { "burst": 2, "per_minute": 1 },
+            "max_ballot_size": 1024,
+        },
+    }));
+    let mut client = server.client();
+    let id = client.get_info().await.unwrap().alternatives[0].id;
+    client.submit_ballot(&ballot(&[(id, 1)])).await.unwrap();
+
+    let rows: Vec<_> = (0..100).map(|rank| (id, rank)).collect();
+    let response = awc::Client::default()
+        .post(format!("{}/api/ballot", server.url()))
+        .send_json(&ballot(&rows))
+        .await
+        .unwrap();
+    assert_eq!(response.status(), 413);
+
+    let mut response = awc::Client::default()
+        .post(format!("{}/api/ballot", server.url()))
+        .header("Accept-Language", "fr")
+        .send_json(&ballot(&[(id, 2)]))
+        .await
+        .unwrap();
+    assert_eq!(response.status(), 429);
+    let retry_after: u64 = response
+        .headers()
+        .get("Retry-After")
+        .unwrap()
+        .to_str()
+        .unwrap()
+        .parse()
+        .unwrap();
+    assert!(retry_after > 0 && retry_after <= 60);
+    let body = response.body().await.unwrap();
+    assert!(std::str::from_utf8(&body)
+        .unwrap()
+        .starts_with("Trop de requêtes"));
+
+    // Reading is not limited
+    client.get_info().await.unwrap();
+}
+
+#[actix_rt::test]
+async fn server_recovers_from_panics() {
+    let server = TestServer::start(false);
+    let mut client = server.client();
+    let id = client.get_info().await.unwrap().alternatives[0].id;
+
+    // Alternative identifiers start at 1
+    assert!(client.submit_ballot(&ballot(&[(0, 1)])).await.is_err());
+    client.submit_ballot(&ballot(&[(id, 1)])).await.unwrap();
+
+    for lock in &["state", "database"] {
+        let response = awc::Client::default()
+            .post(format!("{}/debug/panic/{}", server.url(), lock))
+            .send()
+            .await
+            .unwrap();
+        assert_eq!(response.status(), 500, "{}", lock);
+
+        let readiness = server.get_json("/readyz").await;
+        assert_eq!(readiness["ready"], true, "{}", lock);
+        assert_eq!(
+            client.get_info().await.unwrap().title.as_deref(),
+            Some("Lunch")
+        );
+        assert_eq!(client.get_turnout().await.unwrap().ballots, 1);
+        client.submit_ballot(&ballot(&[(id, 2)])).await.unwrap();
+    }
+}
+
+#[actix_rt::test]
+async fn probes_report_readiness() {
+    let server = TestServer::start(false);
+    let client = awc::Client::default();
+
+    let mut response = client
+        .get(format!("{}/healthz", server.url()))
+        .send()
+        .await
+        .unwrap();
+    assert_eq!(response.status(), 200);
+    let health: Value = response.json().await.unwrap();
+    assert_eq!(health["status"], "ok");
+
+    let mut response = client
+        .get(format!("{}/readyz", server.url()))
+        .send()
+        .await
+        .unwrap();
+    assert_eq!(response.status(), 200);
+    let readiness: Value = response.json().await.unwrap();
+    assert_eq!(readiness["ready"], true);
+    for check in &["state", "config", "database", "schema"] {
+        assert_eq!(readiness["checks"][check]["ok"], true, "{}", check);
+    }
+    assert_eq!(readiness["checks"]["config"]["alternatives"], 3);
+    assert_eq!(readiness["checks"]["schema"]["version"], 3);
+}
+
+/// Data base of the first schema, which had no version, holding a ballot
+const FIRST_SCHEMA: &str = "
+CREATE TABLE elector(
+    elecId INTEGER PRIMARY KEY NOT NULL,
+    elecIp TEXT NOT NULL UNIQUE
+);
+CREATE TABLE alternative(
+    altId INTEGER PRIMARY KEY NOT NULL,
+    altName TEXT UNIQUE,
+    altDescription TEXT,
+    altIcon TEXT
+);
+CREATE TABLE ranking(
+    elecId INTEGER NOT NULL REFERENCES elector(elecId) ON DELETE CASCADE,
+    altId INTEGER NOT NULL REFERENCES alternative(altId) ON DELETE CASCADE,
+    rankMin INTEGER,
+    rankMax INTEGER CHECK(rankMax >= rankMin),
+    PRIMARY KEY(elecId, altId)
+);
+INSERT INTO alternative VALUES(1, 'Pizza', '', ''), (2, 'Sushi', '', ''), (3, 'Salad', '', '');
+INSERT INTO elector VALUES(1, '10.0.0.1');
+INSERT INTO ranking VALUES(1, 1, 1, 1), (1, 2, 3, 3), (1, 3, 2, 2);
+";
+
+#[actix_rt::test]
+async fn old_data_bases_are_migrated() {
+    let directory = tempfile::tempdir().expect("Failed to create directory");
+    let database = directory.path().join("model.db");
+    rusqlite::Connection::open(&database)
+        .unwrap()
+        .execute_batch(FIRST_SCHEMA)
+        .unwrap();
+    let config = json!({
+        "title": "Lunch",
+        "alternatives": [
+            { "id": "Pizza", "description": "", "icon": "" },
+            { "id": "Sushi", "description": "", "icon": "" },
+            { "id": "Salad", "description": "", "icon": "" },
+        ],
+    });
+    fs::write(directory.path().join("election.json"), config.to_string()).unwrap();
+    let server = TestServer::serve(directory);
+
+    let readiness = server.get_json("/readyz").await;
+    assert_eq!(readiness["ready"], true);
+    assert_eq!(readiness["checks"]["schema"]["version"], 3);
+
+    let mut client = server.client();
+    client
+        .submit_ballot(&ballot(&[(1, 2), (2, 1)]))
+        .await
+        .unwrap();
+    let turnout = client.get_turnout().await.unwrap();
+    assert_eq!((turnout.ballots, turnout.weight), (2, 2.0));
+    let csv = client.export_ballots().await.unwrap();
+    assert!(csv.contains(",1.0,Sushi,3,3\n"), "{}", csv);
+    let response = awc::Client::default()
+        .put(format!("{}/api/elector/10.0.0.1", server.url()))
+        .send_json(&json!({ "weight": 0.5 }))
+        .await
+        .unwrap();
+    assert_eq!(response.status(), 204);
+    assert_eq!(client.get_turnout().await.unwrap().weight, 1.5);
+
+    // The ranks of a removed alternative cascade once the foreign keys are on
+    client.remove_alternative(2).await.unwrap();
+    let csv = client.export_ballots().await.unwrap();
+    assert!(!csv.contains("Sushi"), "{}", csv);
+
+    // Versions to come are refused rather than misread
+    let TestServer {
+        server, directory, ..
+    } = server;
+    std::mem::drop(server);
+    rusqlite::Connection::open(directory.path().join("model.db"))
+        .unwrap()
+        .execute_batch("PRAGMA user_version = 99")
+        .unwrap();
+    let path = |file: &str| directory.path().join(file).to_str().unwrap().to_string();
+    match rcvs_web::Server::new(&path("election.json"), &path("model.db")) {
+        Err(what) => assert!(what.to_string().contains("schema version 99"), "{}", what),
+        Ok(_) => panic!("Opened a data base of schema version 99"),
+    }
+}
+
+#[actix_rt::test]
+async fn dangling_rows_are_refused() {
+    let directory = tempfile::tempdir().expect("Failed to create directory");
+    let path = |file: &str| directory.path().join(file).to_str().unwrap().to_string();
+    rusqlite::Connection::open(path("model.db"))
+        .unwrap()
+        .execute_batch(&format!(
+            "{}INSERT INTO ranking VALUES(9, 1, 1, 1);",
+            FIRST_SCHEMA
+        ))
+        .unwrap();
+    fs::write(
+        path("election.json"),
+        json!({ "title": "Lunch", "alternatives": [] }).to_string(),
+    )
+    .unwrap();
+    match rcvs_web::Server::new(&path("election.json"), &path("model.db")) {
+        Err(what) => assert!(what.to_string().contains("missing rows"), "{}", what),
+        Ok(_) => panic!("Opened a data base with a ranking of no elector"),
+    }
+}
+
+#[actix_rt::test]
+async fn metrics_are_exposed() {
+    let server = TestServer::start(false);
+    let mut client = server.client();
+    let id = client.get_info().await.unwrap().alternatives[0].id;
+    client.submit_ballot(&ballot(&[(id, 1)])).await.unwrap();
+    client.get_result().await.unwrap();
+
+    let response = awc::Client::default()
+        .get(format!("{}/metrics", server.url()))
+        .send()
+        .await
+        .unwrap()
+        .body()
+        .await
+        .unwrap();
+    let metrics = std::str::from_utf8(&response).unwrap();
+    for line in &[
+        "rcvs_election_open 1",
+        "rcvs_ballots 1",
+        "rcvs_http_requests_total{method=\"POST\",route=\"/api/ballot\",status=\"204\"}",
+        "rcvs_database_lock_wait_seconds_count",
+        "rcvs_state_lock_wait_seconds_count{mode=\"read\"}",
+        "rcvs_strategy_duration_seconds_count",
+        "rcvs_ballots_cast_total",
+    ] {
+        assert!(
+            metrics.contains(line),
+            "{} is missing from {}",
+            line,
+            metrics
+        );
+    }
+}
+
+#[actix_rt::test]
+async fn remote_scrapers_need_the_metrics_token() {
+    let TestServer {
+        server, directory, ..
+    } = TestServer::start_with(json!({ "metrics": { "token": "scraper-token" } }));
+    std::mem::drop(server);
+    let path = |file: &str| directory.path().join(file).to_str().unwrap().to_string();
+    let app = rcvs_web::Server::new(&path("election.json"), &path("model.db")).unwrap();
+    let mut service =
+        test::init_service(App::new().configure(|config| app.configure(config))).await;
+
+    for (authorization, status) in &[
+        (None, 403),
+        (Some("Bearer wrong-token"), 403),
+        (Some("Basic scraper-token"), 403),
+        (Some("Bearer scraper-token"), 200),
+    ] {
+        let mut request = test::TestRequest::get()
+            .uri("/metrics")
+            .peer_addr("192.0.2.1:40000".parse().unwrap());
+        if let Some(authorization) = authorization {
+            request = request.header("Authorization", *authorization);
+        }
+        let response = test::call_service(&mut service, request.to_request()).await;
+        assert_eq!(response.status(), *status, "{:?}", authorization);
+    }
+}
+
+#[actix_rt::test]
+async fn audit_log_is_chained() {
+    for &anonymous in &[false, true] {
+        let server = TestServer::start(anonymous);
+        let mut client = server.client();
+        let added = client.add_alternative(&edit("Tacos", "")).await.unwrap();
+        client
+            .submit_ballot(&ballot(&[(added.id, 1)]))
+            .await
+            .unwrap();
+        client.delete_ballot().await.unwrap();
+        client.close().await.unwrap();
+
+        let mut log = client.get_audit().await.expect("Failed to get audit log");
+        let events: Vec<&str> = log.iter().map(|e| e.event.as_str()).collect();
+        assert_eq!(
+            events,
+            [
+                "alternative_added",
+                "ballot_cast",
+                "ballot_deleted",
+                "closed"
+            ]
+        );
+        assert_eq!(log[1].data.get("ballot").is_some(), !anonymous);
+        assert!(log[3].data["seed"].is_string());
+        assert_eq!(rcvs_web::model::verify_audit_log(&log), None);
+
+        let export = awc::Client::default()
+            .get(format!("{}/api/export/audit", server.url()))
+            .send()
+            .await
+            .unwrap()
+            .body()
+            .await
+            .unwrap();
+        assert_eq!(
+            std::str::from_utf8(&export).unwrap().lines().count(),
+            log.len()
+        );
+
+        log[1].event = "ballot_deleted".to_string();
+        assert_eq!(rcvs_web::model::verify_audit_log(&log), Some(log[1].id));
+    }
+}
+
+#[test]
+fn mixed_strategy_is_decoded() {
+    let strategy: StrategyData<usize> =
+        serde_json::from_str(r#"{"1": 0.25, "3": 0.75}"#).expect("Failed to decode");
+    match strategy {
+        StrategyData::Mixed(p) => assert_eq!((p[&1], p[&3]), (0.25, 0.75)),
+        StrategyData::Pure(_) => panic!("Decoded as a pure strategy"),
+    }
+    let strategy: StrategyData<usize> = serde_json::from_str("2").expect("Failed to decode");
+    assert!(matches!(strategy, StrategyData::Pure(2)));
+}
+
+#[actix_rt::test]
+async fn api_reference_loads_the_pinned_redoc() {
+    let server = TestServer::start(true);
+    let mut page = awc::Client::default()
+        .get(format!("{}/api/docs", server.url()))
+        .send()
+        .await
+        .unwrap();
+    assert_eq!(page.status(), 200);
+    let html = String::from_utf8(page.body().await.unwrap().to_vec()).unwrap();
+    let script = html
+        .split("<script ")
+        .nth(1)
+        .and_then(|tag| tag.split('>').next())
+        .expect("No script in the page");
+    let attribute = |name: &str| {
+        script
+            .split(&format!("{}=\"", name))
+            .nth(1)
+            .and_then(|value| value.split('"').next())
+            .unwrap_or_else(|| panic!("Script lacks {}", name))
+            .to_string()
+    };
+    let source = attribute("src");
+    assert!(
+        source.starts_with("/api/"),
+        "Redoc is loaded from {}",
+        source
+    );
+
+    let mut response = awc::Client::default()
+        .get(format!("{}{}", server.url(), source))
+        .send()
+        .await
+        .unwrap();
+    assert_eq!(response.status(), 200);
+    assert_eq!(
+        response.headers().get("Content-Type").unwrap(),
+        "application/javascript"
+    );
+    let body = response.body().limit(4 << 20).await.unwrap();
+    let digest = Sha384::digest(&body);
+    assert_eq!(
+        attribute("integrity"),
+        format!("sha384-{}", base64::encode(digest))
+    );
+}
+
+#[actix_rt::test]
+async fn responses_follow_the_openapi_document() {
+    let server = TestServer::start(true);
+    let document = server.get_json("/api/openapi.json").await;
+    assert_eq!(document["openapi"], "3.0.3");
+
+    let info = server.get_json("/api/").await;
+    assert_follows(&document, "ElectionData", &info);
+    assert_follows(
+        &document,
+        "ResultData",
+        &server.get_json("/api/result").await,
+    );
+
+    assert_follows(
+        &document,
+        "TurnoutData",
+        &server.get_json("/api/turnout").await,
+    );
+
+    let id = info["alternatives"][0]["id"].as_i64().unwrap();
+    let created = awc::Client::default()
+        .post(format!("{}/api/ballot", server.url()))
+        .send_json(&ballot(&[(id, 1)]))
+        .await
+        .unwrap()
+        .json()
+        .await
+        .unwrap();
+    assert_follows(&document, "SecretData", &created);
+
+    let mut client = server.client();
+    client.set_secret(created["secret"].as_str().map(|s| s.to_string()));
+    client.close().await.unwrap();
+    assert_follows(
+        &document,
+        "ResultData",
+        &server.get_json("/api/result").await,
+    );
+    let with_ballot = awc::Client::default()
+        .get(format!("{}/api/", server.url()))
+        .set_header("X-Ballot-Secret", client.get_secret().unwrap())
+        .send()
+        .await
+        .unwrap()
+        .json()
+        .await
+        .unwrap();
+    assert_follows(&document, "ElectionData", &with_ballot);
+    for entry in server.get_json("/api/audit").await.as_array().unwrap() {
+        assert_follows(&document, "AuditEntry", entry);
+    }
+}
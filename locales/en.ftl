@@ -0,0 +1,187 @@
+## Shared by all pages
+
+page-loading = Loading…
+page-error = Error
+
+## About page
+
+about-title = About the Randomized Condorcet Voting System
+about-heading = The Randomized Condorcet Voting System
+about-intro =
+    You are about to participate in an experimentation of the Randomized
+    Condorcet Voting System, a state-of-the-art electoral system that could
+    reform our existing electoral systems in a very positive way.
+about-links =
+    If you are here to vote instead, click <a href="/vote">here</a>. If you
+    are here to see the results, click <a href="/result">here</a>.
+about-ready = Are you ready to vote?
+about-cast = Then hop in, and cast your ballot to decide in:
+about-see-result = Or see the result <a href="/result">here</a>.
+
+## Ballot page
+
+vote-no-javascript =
+    JavaScript is disabled: the ballot graph is not shown, but you can still
+    vote with the form below.
+vote-intro =
+    You are about to participate in a fantastic experiment about electoral
+    systems. This web app implements the Randomized Condorcet Voting System
+    (RCVS), which has the wonderful properties of remaining stable with minor
+    candidates and incentivizing honest (nonstrategical) voting.
+vote-how-it-works = How does it work?
+vote-ranges =
+    Below is the list of alternatives with two number fields for each. You may
+    specify one or both, in a way that gives a range of scores for each
+    alternative (<i>e.g.</i> alternative <i>A</i> has a score between 3 and 5).
+    Pairwise comparisons between alternatives are computed as follows:
+vote-rule-unranked =
+    alternatives with no score specified are unranked and never win nor lose
+    against anyone else;
+vote-rule-overlap = alternatives with overlapping ranges are deemed uncomparable;
+vote-rule-prefer =
+    if <i>A</i>’s lower score is greater than <i>B</i>’s higher score, then the
+    elector is said to prefer <i>A</i> over <i>B</i>.
+vote-exaggerating =
+    Exaggerating scores does not favor anyone (<i>e.g.</i> giving a rank of
+    1,000,000 to <i>A</i> and 0 to everyone else is no different from simply
+    giving 1 to <i>A</i>). The ballot’s only effective information is a set of
+    “prefers-over” binary relations. As you modify your ballot, a graph updates
+    to show those binary relations. Once you are set, simply click the “Send”
+    button and let the server do its magic. The “Reload” button reloads your
+    ballot if you already voted. You may modify or delete your ballot for as
+    long as the election is open.
+vote-api =
+    You may also use this app through a REST API. Electors are recognized by
+    their IP address, or by the credential in their personal voting link if the
+    election has a voter roll. If the election is anonymous, your first ballot
+    returns a secret which this page keeps in your browser; it is the only way
+    to modify your ballot later.
+vote-ballot-graph = Ballot graph
+vote-ballot-information = Ballot information
+vote-unranked = Unranked
+vote-lower-score = Lower score
+vote-higher-score = Higher score
+vote-bounds-inverted = Lower bound is bigger than higher bound.
+vote-secret-label = Ballot secret, if you already voted:
+vote-keep-secret = Keep this secret to modify it later:
+vote-send = Send
+vote-reload = Reload
+vote-ballot-loaded = Loaded existing ballot
+vote-not-voted = You have not yet voted
+ballot-sent = Successfully sent ballot
+
+## Result page
+
+result-enable-javascript = Please enable JavaScript to use this HTML interface.
+result-intro =
+    You are viewing the results of the election. To vote, please proceed to the
+    <a href="/vote">ballot page</a>.
+result-duel-graph = Duel graph
+result-duel-graph-explanation =
+    Below is the duel graph of the election. The vertices depict the
+    alternatives to vote for. Each arrow between two alternatives <i>A</i> and
+    <i>B</i> means that more electors said they preferred <i>A</i> over
+    <i>B</i> than the opposite. The absence of arrows means the alternatives
+    are not comparable.
+result-optimal-strategy = Optimal strategy
+result-optimal-strategy-explanation =
+    Below is the optimal strategy that was computed for this election. Picking
+    the winner with this probability distribution will minimize the number of
+    electors wishing a different alternative had won. If there are pairwise
+    equal alternatives, then this distribution may not be unique. If the duel
+    graph is not weakly connected or contains several sources, then it is
+    definitely not unique.
+result-alternative = Alternative
+result-probability = Probability
+result-prospect-winner = Prospect winner
+result-prospect-winner-explanation =
+    Because the duel graph has a unique source, if the election ended now, the
+    winner would be
+result-winner = Winner
+result-winner-by = By
+result-winner-is = , the winner of this election is:
+result-by-condorcet = Condorcet winner
+result-by-strategy = the above probability distribution
+result-elected = Elected alternatives
+result-elected-explanation =
+    The following alternatives were drawn in order, each one from the duel
+    graph of the alternatives not yet elected:
+result-reload = Reload
+result-draws = Number of times winners were drawn:
+result-draws-reopened =
+    The election was reopened after being closed; every draw is kept in its
+    audit log.
+
+## Errors returned to electors
+
+error-missing-credential = Missing voter credential
+error-missing-secret = Missing ballot secret
+error-no-ballot = No ballot detected
+error-election-closed = Election is closed
+error-not-enrolled = Not on the voter roll
+error-secret-required = Already voted; the ballot secret is required to modify the ballot
+error-unknown-secret = Unknown ballot secret
+error-bad-ballot = Bad ballot format: { $reason }
+error-alternative-not-found = { $alternative } is not a valid alternative
+error-invalid-rank-range = [{ $min }, { $max }] is not a valid range
+error-duplicate-alternative = { $alternative } appears twice in the ballot
+error-invalid-rank = { $value } is not a valid rank
+error-correct-ranks = Please correct the highlighted ranks
+error-rate-limited = Too many requests; try again in { $seconds } seconds
+
+## Branding
+
+theme-logo = Home
+
+## Administration
+
+admin-title = Administration
+admin-logout = Log out
+admin-login = Log in
+admin-password = Password
+admin-wrong-password = Wrong password
+admin-no-password =
+    No administrator password is configured: the administration is only
+    available from the server itself.
+admin-status = Status
+admin-open = Open
+admin-closed = Closed
+admin-open-election = Open the election
+admin-close-election = Close the election
+admin-confirm-open =
+    Reopen the election? Electors will be able to vote again; the winners
+    drawn will no longer count, but remain in the audit log.
+admin-confirm-close =
+    Close the election and draw the winners? Electors will no longer be able
+    to vote.
+admin-turnout = Turnout
+admin-ballots = Ballots
+admin-weight = Total weight
+admin-voted = Enrolled voters who voted
+admin-election = Election
+admin-election-title = Title
+admin-save = Save
+admin-saved = Saved
+admin-alternatives = Alternatives
+admin-name = Name
+admin-description = Description (Markdown)
+admin-icon = Icon
+admin-ranked-by = Ballots ranking it
+admin-add = Add
+admin-remove = Remove
+admin-confirm-remove = Remove this alternative along with the ranks it was given?
+admin-audit = Audit log
+admin-audit-explanation = Administrative and voter actions, most recent first. Each entry is chained to the previous one by its hash; rcvs-admin audit verify checks the chain.
+admin-details = Details
+admin-log = Webhooks
+admin-log-explanation = Deliveries of the webhooks, most recent first.
+admin-time = Time
+admin-event = Event
+admin-url = Address
+admin-attempt = Attempt
+admin-outcome = Outcome
+admin-exports = Exports
+admin-export-ballots = Ballots (CSV)
+admin-export-audit = Audit log (JSON Lines)
+admin-export-roll = Voter roll (JSON)
+admin-export-result = Result (JSON)
@@ -0,0 +1,194 @@
+## Commun à toutes les pages
+
+page-loading = Chargement…
+page-error = Erreur
+
+## Page d’accueil
+
+about-title = À propos du scrutin de Condorcet randomisé
+about-heading = Le scrutin de Condorcet randomisé
+about-intro =
+    Vous êtes sur le point de participer à une expérimentation du scrutin de
+    Condorcet randomisé, un mode de scrutin de pointe qui pourrait réformer
+    très positivement nos modes de scrutin actuels.
+about-links =
+    Si vous êtes plutôt là pour voter, cliquez <a href="/vote">ici</a>. Si
+    vous êtes là pour voir les résultats, cliquez <a href="/result">ici</a>.
+about-ready = Prêt à voter ?
+about-cast = Alors allez-y, et déposez votre bulletin pour décider de :
+about-see-result = Ou consultez le résultat <a href="/result">ici</a>.
+
+## Page du bulletin
+
+vote-no-javascript =
+    JavaScript est désactivé : le graphe du bulletin n’est pas affiché, mais
+    vous pouvez tout de même voter avec le formulaire ci-dessous.
+vote-intro =
+    Vous êtes sur le point de participer à une expérience fantastique sur les
+    modes de scrutin. Cette application web met en œuvre le scrutin de
+    Condorcet randomisé (RCVS), qui a la merveilleuse propriété de rester
+    stable face aux petits candidats et d’encourager un vote honnête (non
+    stratégique).
+vote-how-it-works = Comment ça marche ?
+vote-ranges =
+    Ci-dessous se trouve la liste des alternatives, avec deux champs numériques
+    pour chacune. Vous pouvez en remplir un ou les deux, de façon à donner un
+    intervalle de notes à chaque alternative (<i>p. ex.</i> l’alternative
+    <i>A</i> a une note entre 3 et 5). Les alternatives sont comparées deux à
+    deux de la façon suivante :
+vote-rule-unranked =
+    les alternatives sans note ne sont pas classées et ne gagnent ni ne perdent
+    contre aucune autre ;
+vote-rule-overlap =
+    les alternatives dont les intervalles se chevauchent sont jugées
+    incomparables ;
+vote-rule-prefer =
+    si la note basse de <i>A</i> est supérieure à la note haute de <i>B</i>,
+    alors l’électeur est réputé préférer <i>A</i> à <i>B</i>.
+vote-exaggerating =
+    Exagérer les notes ne favorise personne (<i>p. ex.</i> donner le rang
+    1 000 000 à <i>A</i> et 0 à tous les autres revient exactement à donner 1
+    à <i>A</i>). La seule information utile du bulletin est un ensemble de
+    relations binaires « préféré à ». À mesure que vous modifiez votre
+    bulletin, un graphe se met à jour pour montrer ces relations. Une fois
+    prêt, cliquez simplement sur le bouton « Envoyer » et laissez le serveur
+    faire le reste. Le bouton « Recharger » recharge votre bulletin si vous
+    avez déjà voté. Vous pouvez modifier ou supprimer votre bulletin tant que
+    l’élection est ouverte.
+vote-api =
+    Vous pouvez aussi utiliser cette application au moyen d’une API REST. Les
+    électeurs sont reconnus par leur adresse IP, ou par l’identifiant de leur
+    lien de vote personnel si l’élection a une liste électorale. Si l’élection
+    est anonyme, votre premier bulletin renvoie un secret que cette page
+    conserve dans votre navigateur ; c’est le seul moyen de modifier votre
+    bulletin par la suite.
+vote-ballot-graph = Graphe du bulletin
+vote-ballot-information = Votre bulletin
+vote-unranked = Non classée
+vote-lower-score = Note basse
+vote-higher-score = Note haute
+vote-bounds-inverted = La borne basse est supérieure à la borne haute.
+vote-secret-label = Secret du bulletin, si vous avez déjà voté :
+vote-keep-secret = Conservez ce secret pour le modifier plus tard :
+vote-send = Envoyer
+vote-reload = Recharger
+vote-ballot-loaded = Bulletin existant chargé
+vote-not-voted = Vous n’avez pas encore voté
+ballot-sent = Bulletin envoyé
+
+## Page des résultats
+
+result-enable-javascript = Veuillez activer JavaScript pour utiliser cette interface HTML.
+result-intro =
+    Vous consultez les résultats de l’élection. Pour voter, rendez-vous sur la
+    <a href="/vote">page du bulletin</a>.
+result-duel-graph = Graphe des duels
+result-duel-graph-explanation =
+    Ci-dessous se trouve le graphe des duels de l’élection. Les sommets
+    représentent les alternatives soumises au vote. Chaque flèche entre deux
+    alternatives <i>A</i> et <i>B</i> signifie que plus d’électeurs ont
+    déclaré préférer <i>A</i> à <i>B</i> que l’inverse. L’absence de flèche
+    signifie que les alternatives ne sont pas comparables.
+result-optimal-strategy = Stratégie optimale
+result-optimal-strategy-explanation =
+    Ci-dessous se trouve la stratégie optimale calculée pour cette élection.
+    Tirer le vainqueur selon cette loi de probabilité minimise le nombre
+    d’électeurs qui auraient préféré une autre alternative. S’il existe des
+    alternatives deux à deux égales, cette loi peut ne pas être unique. Si le
+    graphe des duels n’est pas faiblement connexe ou contient plusieurs
+    sources, elle n’est certainement pas unique.
+result-alternative = Alternative
+result-probability = Probabilité
+result-prospect-winner = Vainqueur pressenti
+result-prospect-winner-explanation =
+    Comme le graphe des duels a une unique source, si l’élection se terminait
+    maintenant, le vainqueur serait
+result-winner = Vainqueur
+result-winner-by = Par
+result-winner-is = , le vainqueur de cette élection est :
+result-by-condorcet = vainqueur de Condorcet
+result-by-strategy = la loi de probabilité ci-dessus
+result-elected = Alternatives élues
+result-elected-explanation =
+    Les alternatives suivantes ont été tirées dans l’ordre, chacune à partir
+    du graphe des duels des alternatives pas encore élues :
+result-reload = Recharger
+result-draws = Nombre de tirages des vainqueurs :
+result-draws-reopened =
+    L’élection a été rouverte après avoir été close ; chaque tirage est
+    conservé dans son journal d’audit.
+
+## Erreurs renvoyées aux électeurs
+
+error-missing-credential = Identifiant d’électeur manquant
+error-missing-secret = Secret du bulletin manquant
+error-no-ballot = Aucun bulletin trouvé
+error-election-closed = L’élection est close
+error-not-enrolled = Absent de la liste électorale
+error-secret-required = Déjà voté ; le secret du bulletin est nécessaire pour le modifier
+error-unknown-secret = Secret du bulletin inconnu
+error-bad-ballot = Bulletin mal formé : { $reason }
+error-alternative-not-found = { $alternative } n’est pas une alternative valide
+error-invalid-rank-range = [{ $min }, { $max }] n’est pas un intervalle valide
+error-duplicate-alternative = { $alternative } apparaît deux fois dans le bulletin
+error-invalid-rank = { $value } n’est pas un rang valide
+error-correct-ranks = Veuillez corriger les rangs signalés
+error-rate-limited = Trop de requêtes ; réessayez dans { $seconds } secondes
+
+## Identité visuelle
+
+theme-logo = Accueil
+
+## Administration
+
+admin-title = Administration
+admin-logout = Se déconnecter
+admin-login = Se connecter
+admin-password = Mot de passe
+admin-wrong-password = Mot de passe erroné
+admin-no-password =
+    Aucun mot de passe d’administration n’est configuré : l’administration
+    n’est accessible que depuis le serveur lui-même.
+admin-status = État
+admin-open = Ouverte
+admin-closed = Close
+admin-open-election = Ouvrir l’élection
+admin-close-election = Clore l’élection
+admin-confirm-open =
+    Rouvrir l’élection ? Les électeurs pourront de nouveau voter ; les
+    vainqueurs tirés ne compteront plus, mais resteront dans le journal
+    d’audit.
+admin-confirm-close =
+    Clore l’élection et tirer les vainqueurs ? Les électeurs ne pourront plus
+    voter.
+admin-turnout = Participation
+admin-ballots = Bulletins
+admin-weight = Poids total
+admin-voted = Électeurs inscrits ayant voté
+admin-election = Élection
+admin-election-title = Titre
+admin-save = Enregistrer
+admin-saved = Enregistré
+admin-alternatives = Alternatives
+admin-name = Nom
+admin-description = Description (Markdown)
+admin-icon = Icône
+admin-ranked-by = Bulletins qui la classent
+admin-add = Ajouter
+admin-remove = Supprimer
+admin-confirm-remove = Supprimer cette alternative ainsi que les rangs qui lui ont été donnés ?
+admin-audit = Journal d’audit
+admin-audit-explanation = Actions des administrateurs et des électeurs, de la plus récente à la plus ancienne. Chaque entrée est chaînée à la précédente par son empreinte ; rcvs-admin audit verify vérifie la chaîne.
+admin-details = Détails
+admin-log = Webhooks
+admin-log-explanation = Envois des webhooks, du plus récent au plus ancien.
+admin-time = Date
+admin-event = Événement
+admin-url = Adresse
+admin-attempt = Tentative
+admin-outcome = Issue
+admin-exports = Exports
+admin-export-ballots = Bulletins (CSV)
+admin-export-audit = Journal d’audit (JSON Lines)
+admin-export-roll = Liste électorale (JSON)
+admin-export-result = Résultat (JSON)
@@ -0,0 +1,66 @@
+-- From the first schema, which had no version: electors gain a voter roll
+-- entry and a weight, and the tables of anonymous ballots, webhooks, draws
+-- and the audit log are created.
+CREATE TABLE newElector(
+    elecId INTEGER PRIMARY KEY NOT NULL,
+    elecIp TEXT UNIQUE,
+    elecCredential TEXT UNIQUE,
+    elecName TEXT,
+    elecEmail TEXT UNIQUE,
+    elecGroup TEXT,
+    elecWeight INTEGER NOT NULL DEFAULT 1 CHECK(elecWeight >= 0),
+    elecVoted INTEGER NOT NULL DEFAULT 0,
+    CHECK(elecIp IS NOT NULL OR elecCredential IS NOT NULL)
+);
+INSERT INTO newElector(elecId, elecIp, elecVoted)
+SELECT elecId, elecIp, EXISTS(SELECT 1 FROM ranking WHERE ranking.elecId = elector.elecId)
+FROM elector;
+DROP TABLE elector;
+ALTER TABLE newElector RENAME TO elector;
+
+CREATE TABLE sealedBallot(
+    ballotId TEXT PRIMARY KEY NOT NULL,
+    ballotWeight INTEGER NOT NULL DEFAULT 1 CHECK(ballotWeight >= 0)
+) WITHOUT ROWID;
+
+CREATE TABLE sealedRanking(
+    ballotId TEXT NOT NULL REFERENCES sealedBallot(ballotId) ON DELETE CASCADE,
+    altId INTEGER NOT NULL REFERENCES alternative(altId) ON DELETE CASCADE,
+    rankMin INTEGER,
+    rankMax INTEGER CHECK(rankMax >= rankMin),
+    PRIMARY KEY(ballotId, altId)
+) WITHOUT ROWID;
+
+CREATE TABLE webhookDelivery(
+    delivId INTEGER PRIMARY KEY NOT NULL,
+    delivUrl TEXT NOT NULL,
+    delivEvent TEXT NOT NULL,
+    delivAttempt INTEGER NOT NULL,
+    delivStatus INTEGER,
+    delivError TEXT,
+    delivTime INTEGER NOT NULL
+);
+
+CREATE TABLE draw(
+    drawId INTEGER PRIMARY KEY NOT NULL,
+    drawTime INTEGER NOT NULL,
+    drawSeed TEXT NOT NULL,
+    drawResult TEXT NOT NULL
+);
+
+CREATE TABLE auditLog(
+    logId INTEGER PRIMARY KEY NOT NULL,
+    logTime INTEGER NOT NULL,
+    logEvent TEXT NOT NULL,
+    logData TEXT NOT NULL,
+    logHash TEXT NOT NULL
+);
+
+CREATE TRIGGER drawNoUpdate BEFORE UPDATE ON draw
+BEGIN SELECT RAISE(ABORT, 'Draws cannot be modified'); END;
+CREATE TRIGGER drawNoDelete BEFORE DELETE ON draw
+BEGIN SELECT RAISE(ABORT, 'Draws cannot be deleted'); END;
+CREATE TRIGGER auditLogNoUpdate BEFORE UPDATE ON auditLog
+BEGIN SELECT RAISE(ABORT, 'The audit log is append-only'); END;
+CREATE TRIGGER auditLogNoDelete BEFORE DELETE ON auditLog
+BEGIN SELECT RAISE(ABORT, 'The audit log is append-only'); END;
@@ -0,0 +1,6 @@
+-- The anonymous mode is recorded once the data base is opened, from the
+-- configuration it was last run with
+CREATE TABLE setting(
+    setKey TEXT PRIMARY KEY NOT NULL,
+    setValue TEXT NOT NULL
+);
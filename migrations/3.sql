@@ -0,0 +1,24 @@
+-- Weights become decimal. Integer weights read back as decimals, so the rows
+-- are copied as they are.
+CREATE TABLE newElector(
+    elecId INTEGER PRIMARY KEY NOT NULL,
+    elecIp TEXT UNIQUE,
+    elecCredential TEXT UNIQUE,
+    elecName TEXT,
+    elecEmail TEXT UNIQUE,
+    elecGroup TEXT,
+    elecWeight REAL NOT NULL DEFAULT 1 CHECK(elecWeight >= 0),
+    elecVoted INTEGER NOT NULL DEFAULT 0,
+    CHECK(elecIp IS NOT NULL OR elecCredential IS NOT NULL)
+);
+INSERT INTO newElector SELECT * FROM elector;
+DROP TABLE elector;
+ALTER TABLE newElector RENAME TO elector;
+
+CREATE TABLE newSealedBallot(
+    ballotId TEXT PRIMARY KEY NOT NULL,
+    ballotWeight REAL NOT NULL DEFAULT 1 CHECK(ballotWeight >= 0)
+) WITHOUT ROWID;
+INSERT INTO newSealedBallot SELECT * FROM sealedBallot;
+DROP TABLE sealedBallot;
+ALTER TABLE newSealedBallot RENAME TO sealedBallot;
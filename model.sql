@@ -1,8 +1,20 @@
 PRAGMA foreign_keys = ON;
+-- Checked by /readyz against model::SCHEMA_VERSION; bump both on every change
+-- and add the migration from the previous version to migrations/
+PRAGMA user_version = 3;
 
+-- Electors are known either by IP address or, when the election has a voter
+-- roll, by the credential issued to them on import.
 CREATE TABLE elector(
     elecId INTEGER PRIMARY KEY NOT NULL,
-    elecIp TEXT NOT NULL UNIQUE
+    elecIp TEXT UNIQUE,
+    elecCredential TEXT UNIQUE,
+    elecName TEXT,
+    elecEmail TEXT UNIQUE,
+    elecGroup TEXT,
+    elecWeight REAL NOT NULL DEFAULT 1 CHECK(elecWeight >= 0),
+    elecVoted INTEGER NOT NULL DEFAULT 0,
+    CHECK(elecIp IS NOT NULL OR elecCredential IS NOT NULL)
 );
 
 CREATE TABLE alternative(
@@ -19,3 +31,65 @@ CREATE TABLE ranking(
     rankMax INTEGER CHECK(rankMax >= rankMin),
     PRIMARY KEY(elecId, altId)
 );
+
+-- Anonymous mode: an elector row only records whether the elector has voted,
+-- while ballots are keyed by the SHA-256 digest of a secret held by the voter.
+-- WITHOUT ROWID keeps rows ordered by digest rather than by insertion time.
+CREATE TABLE sealedBallot(
+    ballotId TEXT PRIMARY KEY NOT NULL,
+    ballotWeight REAL NOT NULL DEFAULT 1 CHECK(ballotWeight >= 0)
+) WITHOUT ROWID;
+
+CREATE TABLE sealedRanking(
+    ballotId TEXT NOT NULL REFERENCES sealedBallot(ballotId) ON DELETE CASCADE,
+    altId INTEGER NOT NULL REFERENCES alternative(altId) ON DELETE CASCADE,
+    rankMin INTEGER,
+    rankMax INTEGER CHECK(rankMax >= rankMin),
+    PRIMARY KEY(ballotId, altId)
+) WITHOUT ROWID;
+
+CREATE TABLE webhookDelivery(
+    delivId INTEGER PRIMARY KEY NOT NULL,
+    delivUrl TEXT NOT NULL,
+    delivEvent TEXT NOT NULL,
+    delivAttempt INTEGER NOT NULL,
+    delivStatus INTEGER,
+    delivError TEXT,
+    delivTime INTEGER NOT NULL
+);
+
+-- Settings fixed when the data base is created, such as whether the election
+-- is anonymous, and markers of things done once, such as the reminder sent
+CREATE TABLE setting(
+    setKey TEXT PRIMARY KEY NOT NULL,
+    setValue TEXT NOT NULL
+);
+
+-- Every draw of the winners, with the seed of the generator it was made with
+-- and the result it published
+CREATE TABLE draw(
+    drawId INTEGER PRIMARY KEY NOT NULL,
+    drawTime INTEGER NOT NULL,
+    drawSeed TEXT NOT NULL,
+    drawResult TEXT NOT NULL
+);
+
+-- Administrative and voter actions. Each entry holds the SHA-256 digest of
+-- the previous one along with its own content. Neither table can be modified
+-- once written.
+CREATE TABLE auditLog(
+    logId INTEGER PRIMARY KEY NOT NULL,
+    logTime INTEGER NOT NULL,
+    logEvent TEXT NOT NULL,
+    logData TEXT NOT NULL,
+    logHash TEXT NOT NULL
+);
+
+CREATE TRIGGER drawNoUpdate BEFORE UPDATE ON draw
+BEGIN SELECT RAISE(ABORT, 'Draws cannot be modified'); END;
+CREATE TRIGGER drawNoDelete BEFORE DELETE ON draw
+BEGIN SELECT RAISE(ABORT, 'Draws cannot be deleted'); END;
+CREATE TRIGGER auditLogNoUpdate BEFORE UPDATE ON auditLog
+BEGIN SELECT RAISE(ABORT, 'The audit log is append-only'); END;
+CREATE TRIGGER auditLogNoDelete BEFORE DELETE ON auditLog
+BEGIN SELECT RAISE(ABORT, 'The audit log is append-only'); END;
@@ -0,0 +1,190 @@
+use std::collections::HashMap;
+use std::sync::Mutex;
+use std::time::{SystemTime, UNIX_EPOCH};
+
+use actix_web::cookie::{Cookie, SameSite};
+use actix_web::http::header;
+use actix_web::{HttpMessage, HttpRequest, HttpResponse};
+use rand::RngCore;
+use serde::Deserialize;
+use sha2::{Digest, Sha256};
+
+/// Cookie holding the session of an administrator logged in from a browser
+pub const SESSION_COOKIE: &str = "rcvs-admin";
+
+/// Header through which the pages of a session present its CSRF token
+pub const CSRF_HEADER: &str = "X-CSRF-Token";
+
+/// Seconds a session lasts after the login
+const SESSION_DURATION: u64 = 8 * 3600;
+
+#[derive(Deserialize, Clone, Debug)]
+pub struct AdminConfig {
+    /// Hexadecimal SHA-256 of the password, so that the configuration does
+    /// not give it away
+    password_sha256: String,
+}
+
+#[derive(Debug)]
+struct Session {
+    expiry: u64,
+    /// Token the dashboard sends back in a header, which other sites cannot
+    /// read and have a browser send along with the cookie
+    csrf: String,
+}
+
+/// Sessions of the administrators logged in with the password of the
+/// configuration. Requests from the loopback interface need none.
+#[derive(Debug)]
+pub struct Admin {
+    config: Option<AdminConfig>,
+    sessions: Mutex<HashMap<String, Session>>,
+}
+
+fn now() -> u64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|d| d.as_secs())
+        .unwrap_or(0)
+}
+
+fn make_token() -> String {
+    let mut bytes = [0u8; 32];
+    rand::thread_rng().fill_bytes(&mut bytes);
+    hex::encode(bytes)
+}
+
+/// Compares in a time independent of where the strings differ
+pub fn same_digest(a: &str, b: &str) -> bool {
+    a.len() == b.len()
+        && a.bytes()
+            .zip(b.bytes())
+            .fold(0, |difference, (x, y)| difference | (x ^ y))
+            == 0
+}
+
+impl Admin {
+    pub fn new(config: Option<AdminConfig>) -> Self {
+        Self {
+            config: config,
+            sessions: Mutex::new(HashMap::new()),
+        }
+    }
+
+    /// Whether administrators can log in from other addresses than loopback
+    pub fn has_password(&self) -> bool {
+        self.config.is_some()
+    }
+
+    /// Opens a session if the password is right. Returns its token.
+    pub fn login(&self, password: &str) -> Option<String> {
+        let config = self.config.as_ref()?;
+        let digest = hex::encode(Sha256::digest(password.as_bytes()));
+        if !same_digest(&digest, &config.password_sha256.to_lowercase()) {
+            return None;
+        }
+        let token = make_token();
+        let mut sessions = self.sessions.lock().ok()?;
+        let now = now();
+        sessions.retain(|_, session| session.expiry > now);
+        sessions.insert(
+            token.to_string(),
+            Session {
+                expiry: now + SESSION_DURATION,
+                csrf: make_token(),
+            },
+        );
+        Some(token)
+    }
+
+    pub fn logout(&self, req: &HttpRequest) {
+        if let (Some(cookie), Ok(mut sessions)) = (req.cookie(SESSION_COOKIE), self.sessions.lock())
+        {
+            sessions.remove(cookie.value());
+        }
+    }
+
+    /// CSRF token of the session of the request, if it has a valid one
+    pub fn csrf_token(&self, req: &HttpRequest) -> Option<String> {
+        let cookie = req.cookie(SESSION_COOKIE)?;
+        let sessions = self.sessions.lock().ok()?;
+        sessions
+            .get(cookie.value())
+            .filter(|session| session.expiry > now())
+            .map(|session| session.csrf.to_string())
+    }
+
+    /// Lets requests from loopback or with a session through. Returns the
+    /// CSRF token of the session if it was needed. `action` ends the message
+    /// of the refusal.
+    fn check(&self, req: &HttpRequest, action: &str) -> Result<Option<String>, HttpResponse> {
+        let ip = match req.peer_addr() {
+            Some(a) => a.ip(),
+            None => {
+                return Err(HttpResponse::InternalServerError()
+                    .body("Failed to retrieve client IP address"))
+            }
+        };
+
+        if ip.is_loopback() {
+            return Ok(None);
+        }
+        match self.csrf_token(req) {
+            Some(csrf) => Ok(Some(csrf)),
+            None => Err(HttpResponse::Forbidden()
+                .body(&format!("Only loopback or an administrator can {}", action))),
+        }
+    }
+
+    /// Authorizes a request that reads
+    pub fn authorize(&self, req: &HttpRequest, action: &str) -> Result<(), HttpResponse> {
+        self.check(req, action).map(|_| ())
+    }
+
+    /// Authorizes a request that changes the election. Browsers can be made
+    /// to send those from other sites: they are refused if they come from
+    /// another origin, or rely on a session without presenting its CSRF
+    /// token.
+    pub fn authorize_change(&self, req: &HttpRequest, action: &str) -> Result<(), HttpResponse> {
+        if is_cross_origin(req) {
+            return Err(HttpResponse::Forbidden().body("Cross-origin request refused"));
+        }
+        match self.check(req, action)? {
+            Some(csrf) => {
+                let presented = req
+                    .headers()
+                    .get(CSRF_HEADER)
+                    .and_then(|value| value.to_str().ok())
+                    .unwrap_or_default();
+                if same_digest(presented, &csrf) {
+                    Ok(())
+                } else {
+                    Err(HttpResponse::Forbidden().body("Missing or wrong CSRF token"))
+                }
+            }
+            None => Ok(()),
+        }
+    }
+}
+
+/// Browsers tell where a request comes from in its `Origin` header, which
+/// other clients leave out
+pub fn is_cross_origin(req: &HttpRequest) -> bool {
+    let origin = match req.headers().get(header::ORIGIN) {
+        Some(origin) => origin.to_str().unwrap_or_default(),
+        None => return false,
+    };
+    let host = req.connection_info().host().to_string();
+    origin.splitn(2, "://").nth(1) != Some(host.as_str())
+}
+
+/// Marked secure when the request came over HTTPS, so that the browser never
+/// sends it in the clear
+pub fn session_cookie(req: &HttpRequest, token: &str) -> Cookie<'static> {
+    Cookie::build(SESSION_COOKIE, token.to_string())
+        .path("/")
+        .http_only(true)
+        .secure(req.connection_info().scheme() == "https")
+        .same_site(SameSite::Strict)
+        .finish()
+}
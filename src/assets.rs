@@ -0,0 +1,106 @@
+use std::path::{Path, PathBuf};
+use std::{error::Error, fmt, fs, io, io::Cursor};
+
+use image::io::Reader;
+use image::{ImageFormat, ImageOutputFormat};
+use sha2::{Digest, Sha256};
+
+/// Path under which the uploaded files are served
+pub const ASSETS_URL: &str = "/assets";
+
+/// Directory of the uploaded files, next to the data base rather than in the
+/// working directory, so that they are backed up and moved along with it
+pub fn directory(database: &str) -> PathBuf {
+    Path::new(database)
+        .parent()
+        .unwrap_or_else(|| Path::new(""))
+        .join("assets")
+}
+
+/// Largest upload accepted, in bytes
+pub const MAX_UPLOAD_SIZE: usize = 2 << 20;
+
+/// Larger images are refused before being decoded
+const MAX_DIMENSION: u32 = 4096;
+
+/// Icons are scaled down to fit in a square of this side
+const ICON_SIZE: u32 = 256;
+
+#[derive(Debug)]
+pub enum IconError {
+    UnsupportedType,
+    TooLarge(u32, u32),
+    Invalid(String),
+    Io(io::Error),
+}
+
+impl Error for IconError {}
+
+impl fmt::Display for IconError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Self::UnsupportedType => write!(f, "Only PNG, JPEG, GIF and WebP images are accepted"),
+            Self::TooLarge(w, h) => write!(
+                f,
+                "{}×{} is larger than {}×{}",
+                w, h, MAX_DIMENSION, MAX_DIMENSION
+            ),
+            Self::Invalid(what) => write!(f, "Invalid image: {}", what),
+            Self::Io(what) => write!(f, "Failed to store image: {}", what),
+        }
+    }
+}
+
+fn open(data: &[u8]) -> Result<Reader<Cursor<&[u8]>>, IconError> {
+    Reader::new(Cursor::new(data))
+        .with_guessed_format()
+        .map_err(IconError::Io)
+}
+
+/// Re-encodes the image as a PNG thumbnail, so that only images decoded by the
+/// server are ever served. The file is named after its contents so that it can
+/// be cached for good. Returns the path under which it is served.
+pub fn store_icon(directory: &Path, alternative: i64, data: &[u8]) -> Result<String, IconError> {
+    let format = match open(data)?.format() {
+        Some(ImageFormat::Png) => ImageFormat::Png,
+        Some(ImageFormat::Jpeg) => ImageFormat::Jpeg,
+        Some(ImageFormat::Gif) => ImageFormat::Gif,
+        Some(ImageFormat::WebP) => ImageFormat::WebP,
+        _ => return Err(IconError::UnsupportedType),
+    };
+    let (width, height) = open(data)?
+        .into_dimensions()
+        .map_err(|what| IconError::Invalid(what.to_string()))?;
+    if width > MAX_DIMENSION || height > MAX_DIMENSION {
+        return Err(IconError::TooLarge(width, height));
+    }
+
+    let image = image::load_from_memory_with_format(data, format)
+        .map_err(|what| IconError::Invalid(what.to_string()))?;
+    let mut png = Vec::new();
+    image
+        .thumbnail(ICON_SIZE, ICON_SIZE)
+        .write_to(&mut png, ImageOutputFormat::Png)
+        .map_err(|what| IconError::Invalid(what.to_string()))?;
+
+    let digest = hex::encode(Sha256::digest(&png));
+    let name = format!("icons/{}-{}.png", alternative, &digest[..16]);
+    let path = directory.join(&name);
+    if let Some(directory) = path.parent() {
+        fs::create_dir_all(directory).map_err(IconError::Io)?;
+    }
+    fs::write(&path, &png).map_err(IconError::Io)?;
+    Ok(format!("{}/{}", ASSETS_URL, name))
+}
+
+/// Deletes an icon stored by `store_icon`. Icons hosted elsewhere are ignored.
+pub fn remove_icon(directory: &Path, icon: &str) {
+    let name = match icon.strip_prefix(&format!("{}/icons/", ASSETS_URL)) {
+        Some(name) if !name.contains('/') && !name.starts_with('.') => name,
+        _ => return,
+    };
+    let path = directory.join("icons").join(name);
+    if let Err(what) = fs::remove_file(&path) {
+        tracing::warn!(path = %path.display(), error = %what, "Failed to remove icon");
+    }
+}
@@ -5,6 +5,8 @@ use std::iter::FromIterator;
 
 use serde::{Deserialize, Serialize};
 
+pub use rcvs_web_types::StrategyData;
+
 #[derive(Deserialize, Clone, Debug)]
 pub struct AlternativeData {
     pub id: String,
@@ -12,19 +14,10 @@ pub struct AlternativeData {
     pub icon: String,
 }
 
-#[derive(Serialize, Debug, Clone)]
-#[serde(untagged)]
-pub enum StrategyData<V: Serialize + Eq + Hash> {
-    Pure(V),
-    Mixed(HashMap<V, f64>),
-}
-
-impl<V: Serialize + Eq + Hash + Clone> StrategyData<V> {
-    pub fn new(strategy: &rcvs::Strategy<V>) -> Self {
-        match strategy {
-            rcvs::Strategy::Pure(a) => Self::Pure(a.to_owned()),
-            rcvs::Strategy::Mixed(p) => Self::Mixed(HashMap::from_iter(p.iter().cloned())),
-        }
+pub fn strategy_data<V: Eq + Hash + Clone>(strategy: &rcvs::Strategy<V>) -> StrategyData<V> {
+    match strategy {
+        rcvs::Strategy::Pure(a) => StrategyData::Pure(a.to_owned()),
+        rcvs::Strategy::Mixed(p) => StrategyData::Mixed(HashMap::from_iter(p.iter().cloned())),
     }
 }
 
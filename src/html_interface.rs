@@ -1,49 +1,296 @@
-use std::io::BufRead;
+use std::collections::HashMap;
+use std::error::Error;
+use std::fs;
 
-use actix_files::NamedFile;
+use actix_web::http::StatusCode;
 use actix_web::HttpResponse;
+use askama::Template;
+use serde::Deserialize;
 
-fn read_page(file_name: &str) -> actix_web::Result<NamedFile> {
-    let path: std::path::PathBuf = file_name.parse()?;
-    let file = NamedFile::open(path)?
-        .set_content_type("text/html; charset=utf-8".parse::<mime::Mime>().unwrap());
-    Ok(file)
+use crate::i18n::Translator;
+use crate::model;
+
+/// Colors of the pages. Each one is any CSS color.
+#[derive(Deserialize, Clone, Debug, Default)]
+pub struct Palette {
+    #[serde(default)]
+    background: Option<String>,
+    #[serde(default)]
+    text: Option<String>,
+    #[serde(default)]
+    accent: Option<String>,
+    #[serde(default)]
+    link: Option<String>,
 }
 
-pub fn preprocess_page(file_name: &str, name: &str) -> actix_web::HttpResponse {
-    let file = match std::fs::File::open(file_name) {
-        Ok(f) => f,
-        Err(what) => {
-            return HttpResponse::InternalServerError()
-                .body(&format!("Failed to open file: {}", what))
-        }
-    };
-    let lines = std::io::BufReader::new(file).lines();
-    let mut processed = String::new();
-    for line in lines {
-        match line {
-            Ok(line) => {
-                let s = line.replace("$NAME", name);
-                processed.push_str(&s);
+#[derive(Deserialize, Clone, Debug)]
+pub struct FooterLink {
+    pub label: String,
+    pub url: String,
+}
+
+/// Branding shared by all the pages of an election
+#[derive(Deserialize, Clone, Debug, Default)]
+pub struct Theme {
+    #[serde(default)]
+    pub logo: Option<String>,
+    #[serde(default)]
+    palette: Palette,
+    /// Path of a stylesheet added after the default style of every page
+    #[serde(default)]
+    stylesheet: Option<String>,
+    #[serde(skip)]
+    pub css: String,
+    /// Replaces the essay on the home page; paragraphs are separated by blank
+    /// lines
+    #[serde(default)]
+    pub intro: Option<String>,
+    #[serde(default)]
+    pub footer: Vec<FooterLink>,
+}
+
+/// Keeps colors from closing the rule they are inserted in
+fn is_color(color: &str) -> bool {
+    color
+        .chars()
+        .all(|c| c.is_ascii_alphanumeric() || "#(),.% ".contains(c))
+}
+
+impl Theme {
+    /// Checks the palette and reads the stylesheet
+    pub fn load(&mut self) -> Result<(), Box<dyn Error>> {
+        let palette = &self.palette;
+        for color in [
+            &palette.background,
+            &palette.text,
+            &palette.accent,
+            &palette.link,
+        ]
+        .iter()
+        .filter_map(|c| c.as_ref())
+        {
+            if !is_color(color) {
+                return Err(format!("{} is not a valid color", color).into());
             }
-            Err(what) => {
-                return HttpResponse::InternalServerError()
-                    .body(&format!("Failed to preprocess file: {}", what))
+        }
+        if let Some(path) = &self.stylesheet {
+            let css = fs::read_to_string(path)?;
+            if css.contains("</") {
+                return Err("The stylesheet must not contain \"</\"".into());
             }
+            self.css = css;
         }
+        Ok(())
+    }
+
+    pub fn palette_css(&self) -> String {
+        let mut css = String::new();
+        if let Some(color) = &self.palette.background {
+            css += &format!("body {{ background-color: {}; }}\n", color);
+        }
+        if let Some(color) = &self.palette.text {
+            css += &format!("body {{ color: {}; }}\n", color);
+        }
+        if let Some(color) = &self.palette.accent {
+            css += &format!(
+                "h1, h2, h3, h4 {{ color: {0}; }}\nbutton {{ border-color: {0}; }}\n",
+                color
+            );
+        }
+        if let Some(color) = &self.palette.link {
+            css += &format!("a {{ color: {}; }}\n", color);
+        }
+        css
+    }
+
+    pub fn intro_paragraphs(&self) -> Vec<&str> {
+        self.intro
+            .as_deref()
+            .unwrap_or_default()
+            .split("\n\n")
+            .map(|p| p.trim())
+            .filter(|p| !p.is_empty())
+            .collect()
+    }
+}
+
+#[derive(Template)]
+#[template(path = "about.html")]
+pub struct AboutPage<'a> {
+    pub t: Translator<'a>,
+    pub theme: &'a Theme,
+    pub title: &'a str,
+}
+
+/// Reference of the REST API, rendered by Redoc from `/api/openapi.json`
+#[derive(Template)]
+#[template(path = "api.html")]
+pub struct ApiPage<'a> {
+    pub title: &'a str,
+}
+
+/// Dashboard of the administrators, which loads everything from the API
+#[derive(Template)]
+#[template(path = "admin.html")]
+pub struct AdminPage<'a> {
+    pub t: Translator<'a>,
+    pub theme: &'a Theme,
+    pub title: &'a str,
+    /// CSRF token of the session, if the administrator logged in rather
+    /// than using loopback
+    pub csrf: Option<String>,
+}
+
+#[derive(Template)]
+#[template(path = "admin_login.html")]
+pub struct AdminLoginPage<'a> {
+    pub t: Translator<'a>,
+    pub theme: &'a Theme,
+    pub title: &'a str,
+    pub has_password: bool,
+    pub error: Option<String>,
+}
+
+/// Row of the ballot table, with the bounds already entered by the elector
+pub struct BallotLine {
+    pub id: i64,
+    /// Sanitized HTML
+    pub description: String,
+    pub icon: String,
+    pub low: String,
+    pub high: String,
+    pub error: Option<String>,
+}
+
+#[derive(Template)]
+#[template(path = "vote.html")]
+pub struct VotePage<'a> {
+    pub t: Translator<'a>,
+    pub theme: &'a Theme,
+    pub title: &'a str,
+    pub election_json: String,
+    pub rows: Vec<BallotLine>,
+    pub anonymous: bool,
+    pub error: Option<String>,
+    pub confirmation: Option<String>,
+    pub secret: Option<String>,
+}
+
+#[derive(Template)]
+#[template(path = "result.html")]
+pub struct ResultPage<'a> {
+    pub t: Translator<'a>,
+    pub theme: &'a Theme,
+    pub title: &'a str,
+    pub result_json: String,
+    pub winner: Option<String>,
+    pub winners: Vec<String>,
+    /// Number of times winners were ever drawn
+    pub draws: usize,
+}
+
+/// Lines up the ballot with the alternatives. As in the JavaScript interface,
+/// the higher bound is left blank when it equals the lower one.
+pub fn ballot_lines(
+    alternatives: &[model::AlternativeData],
+    ballot: &[model::BallotRow],
+) -> Vec<BallotLine> {
+    alternatives
+        .iter()
+        .map(|alternative| {
+            let row = ballot
+                .iter()
+                .find(|row| row.alternative as i64 == alternative.id);
+            BallotLine {
+                id: alternative.id,
+                description: alternative.description_html.to_string(),
+                icon: alternative.icon.to_string(),
+                low: row.map(|r| r.min.to_string()).unwrap_or_default(),
+                high: row
+                    .filter(|r| r.min != r.max)
+                    .map(|r| r.max.to_string())
+                    .unwrap_or_default(),
+                error: None,
+            }
+        })
+        .collect()
+}
+
+fn parse_rank(value: &str, t: &Translator) -> Result<Option<u64>, String> {
+    let value = value.trim();
+    if value.is_empty() {
+        Ok(None)
+    } else {
+        value
+            .parse()
+            .map(Some)
+            .map_err(|_| t.tr_with("error-invalid-rank", &[("value", value.to_string())]))
     }
-    HttpResponse::Ok()
-        .set_header(
-            actix_web::http::header::CONTENT_TYPE,
-            "text/html; charset=utf-8",
-        )
-        .body(processed)
 }
 
-pub async fn vote() -> actix_web::Result<NamedFile> {
-    read_page("vote.html")
+/// Reads the ballot from the fields `<id>-low` and `<id>-high` of the vote
+/// form. A blank bound takes the value of the other one and an alternative
+/// with both bounds blank is left unranked. The ballot is only returned if
+/// every field could be read; the lines keep what the elector typed.
+pub fn parse_ballot_form(
+    alternatives: &[model::AlternativeData],
+    form: &HashMap<String, String>,
+    t: &Translator,
+) -> (Vec<BallotLine>, Option<Vec<model::BallotRow>>) {
+    let mut lines = Vec::new();
+    let mut ballot = Some(Vec::new());
+    for alternative in alternatives {
+        let low = form
+            .get(&format!("{}-low", alternative.id))
+            .map(|v| v.trim().to_string())
+            .unwrap_or_default();
+        let high = form
+            .get(&format!("{}-high", alternative.id))
+            .map(|v| v.trim().to_string())
+            .unwrap_or_default();
+        let error = match (parse_rank(&low, t), parse_rank(&high, t)) {
+            (Ok(min), Ok(max)) => {
+                if let (Some(ballot), Some(min)) = (&mut ballot, min.or(max)) {
+                    ballot.push(model::BallotRow {
+                        elector: None,
+                        alternative: alternative.id as usize,
+                        min: min,
+                        max: max.unwrap_or(min),
+                    });
+                }
+                None
+            }
+            (Err(what), _) | (_, Err(what)) => {
+                ballot = None;
+                Some(what)
+            }
+        };
+        lines.push(BallotLine {
+            id: alternative.id,
+            description: alternative.description_html.to_string(),
+            icon: alternative.icon.to_string(),
+            low: low,
+            high: high,
+            error: error,
+        });
+    }
+    (lines, ballot)
 }
 
-pub async fn result() -> actix_web::Result<NamedFile> {
-    read_page("result.html")
+pub fn render<T: Template>(page: &T) -> HttpResponse {
+    render_with_status(StatusCode::OK, page)
+}
+
+pub fn render_with_status<T: Template>(status: StatusCode, page: &T) -> HttpResponse {
+    match page.render() {
+        Ok(html) => HttpResponse::build(status)
+            .set_header(
+                actix_web::http::header::CONTENT_TYPE,
+                "text/html; charset=utf-8",
+            )
+            .body(html),
+        Err(what) => {
+            HttpResponse::InternalServerError().body(&format!("Failed to render page: {}", what))
+        }
+    }
 }
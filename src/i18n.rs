@@ -0,0 +1,138 @@
+use std::fmt;
+
+use actix_web::http::header::ACCEPT_LANGUAGE;
+use actix_web::{web, HttpRequest};
+use fluent_bundle::concurrent::FluentBundle;
+use fluent_bundle::{FluentArgs, FluentResource};
+use fluent_langneg::{accepted_languages, negotiate_languages, NegotiationStrategy};
+use serde::Deserialize;
+use unic_langid::LanguageIdentifier;
+
+/// Catalogs compiled into the server. The first one is used when none of the
+/// languages asked for is available, and for messages missing from the others.
+const CATALOGS: &[(&str, &str)] = &[
+    ("en", include_str!("../locales/en.ftl")),
+    ("fr", include_str!("../locales/fr.ftl")),
+];
+
+pub struct Catalogs {
+    languages: Vec<LanguageIdentifier>,
+    bundles: Vec<FluentBundle<FluentResource>>,
+}
+
+impl fmt::Debug for Catalogs {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.debug_struct("Catalogs")
+            .field("languages", &self.languages)
+            .finish()
+    }
+}
+
+#[derive(Deserialize)]
+struct LanguageQuery {
+    lang: Option<String>,
+}
+
+impl Catalogs {
+    pub fn new() -> Self {
+        let mut languages = Vec::new();
+        let mut bundles = Vec::new();
+        for (language, source) in CATALOGS {
+            let language: LanguageIdentifier =
+                language.parse().expect("Catalog language is well-formed");
+            let resource = FluentResource::try_new(source.to_string())
+                .map_err(|(_, errors)| errors)
+                .expect("Catalog is well-formed");
+            let mut bundle = FluentBundle::new_concurrent(vec![language.clone()]);
+            // Isolation marks would end up in plain text responses
+            bundle.set_use_isolating(false);
+            bundle
+                .add_resource(resource)
+                .expect("Catalog has no duplicate message");
+            languages.push(language);
+            bundles.push(bundle);
+        }
+        Self {
+            languages: languages,
+            bundles: bundles,
+        }
+    }
+
+    /// Picks the language of the response: the `lang` query parameter comes
+    /// first, then the `Accept-Language` header
+    pub fn negotiate(&self, req: &HttpRequest) -> Translator<'_> {
+        let mut requested = Vec::new();
+        if let Ok(query) = web::Query::<LanguageQuery>::from_query(req.query_string()) {
+            if let Some(language) = query.lang.as_ref().and_then(|l| l.parse().ok()) {
+                requested.push(language);
+            }
+        }
+        if let Some(header) = req
+            .headers()
+            .get(ACCEPT_LANGUAGE)
+            .and_then(|value| value.to_str().ok())
+        {
+            requested.extend(accepted_languages::parse(header));
+        }
+        let chosen = negotiate_languages(
+            &requested,
+            &self.languages,
+            self.languages.first(),
+            NegotiationStrategy::Lookup,
+        );
+        let index = chosen
+            .first()
+            .and_then(|&c| self.languages.iter().position(|l| l == c))
+            .unwrap_or(0);
+        Translator {
+            language: &self.languages[index],
+            bundle: &self.bundles[index],
+            fallback: &self.bundles[0],
+        }
+    }
+}
+
+/// Messages of the language negotiated for one request
+pub struct Translator<'a> {
+    language: &'a LanguageIdentifier,
+    bundle: &'a FluentBundle<FluentResource>,
+    fallback: &'a FluentBundle<FluentResource>,
+}
+
+impl<'a> Translator<'a> {
+    pub fn lang(&self) -> String {
+        self.language.to_string()
+    }
+
+    pub fn tr(&self, id: &str) -> String {
+        self.format(id, None)
+    }
+
+    pub fn tr_with(&self, id: &str, variables: &[(&str, String)]) -> String {
+        let mut args = FluentArgs::new();
+        for (name, value) in variables {
+            args.set(*name, value.to_string());
+        }
+        self.format(id, Some(&args))
+    }
+
+    /// Message as a JavaScript string literal, for inline scripts
+    pub fn js(&self, id: &str) -> String {
+        serde_json::to_string(&self.tr(id))
+            .unwrap_or_default()
+            .replace("</", "<\\/")
+    }
+
+    fn format(&self, id: &str, args: Option<&FluentArgs>) -> String {
+        for bundle in &[self.bundle, self.fallback] {
+            if let Some(pattern) = bundle.get_message(id).and_then(|m| m.value()) {
+                let mut errors = Vec::new();
+                return bundle
+                    .format_pattern(pattern, args, &mut errors)
+                    .to_string();
+            }
+        }
+        tracing::warn!(id = id, "Missing translation");
+        id.to_string()
+    }
+}
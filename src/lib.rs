@@ -0,0 +1,2237 @@
+use std::collections::{HashMap, HashSet};
+use std::error::Error;
+use std::fmt;
+use std::fs::File;
+use std::io::BufReader;
+use std::mem;
+use std::path::PathBuf;
+use std::sync::Arc;
+use std::time::{Duration, Instant, SystemTime, UNIX_EPOCH};
+
+use actix_web::error::BlockingError;
+use actix_web::http::{header, StatusCode};
+use actix_web::{middleware, web, HttpRequest, HttpResponse, Responder};
+use rand::{RngCore, SeedableRng};
+use serde::{Deserialize, Serialize};
+use tracing::{debug, error, info, Instrument};
+
+mod admin;
+mod assets;
+mod data;
+mod html_interface;
+mod i18n;
+mod logging;
+mod mailer;
+mod markdown;
+mod metrics;
+pub mod model;
+mod openapi;
+mod rate_limit;
+mod recovery;
+mod tls;
+mod webhook;
+
+use data::*;
+use rcvs_web_types::{
+    AlternativeEdit, ArrowData, IconData, ResultData, SecretData, TitleData, WeightData,
+};
+use rcvs_web_types::{BALLOT_SECRET_HEADER, CREDENTIAL_HEADER};
+
+pub use logging::RequestTracing;
+pub use metrics::RequestMetrics;
+pub use recovery::CatchPanic;
+pub use tls::TlsConfig;
+
+#[derive(Deserialize, Clone, Debug)]
+struct ElectionData {
+    title: String,
+    alternatives: Vec<AlternativeData>,
+    #[serde(default)]
+    anonymous: bool,
+    #[serde(default = "default_seats")]
+    seats: usize,
+    #[serde(default)]
+    voter_roll: bool,
+    #[serde(default)]
+    mail: Option<mailer::MailConfig>,
+    #[serde(default)]
+    webhooks: Vec<webhook::WebhookConfig>,
+    #[serde(default)]
+    ballot_milestones: Vec<u64>,
+    #[serde(default)]
+    theme: html_interface::Theme,
+    #[serde(default)]
+    admin: Option<admin::AdminConfig>,
+    /// Whether a closed election can be opened again, discarding its draw
+    #[serde(default)]
+    allow_reopen: bool,
+    #[serde(default)]
+    metrics: metrics::MetricsConfig,
+    #[serde(default)]
+    rate_limit: rate_limit::RateLimitConfig,
+    /// Serves HTTPS rather than plain HTTP on the loopback interface
+    #[serde(default)]
+    tls: Option<tls::TlsConfig>,
+}
+
+fn default_seats() -> usize {
+    1
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug)]
+struct BallotData {
+    alternative: String,
+    min: u64,
+    max: u64,
+}
+
+#[derive(Clone, Debug)]
+enum BallotValidityError<V> {
+    AlternativeNotFound(V),
+    InvalidRankRange(u64, u64),
+    DuplicateAlternative(V),
+}
+
+impl<V: fmt::Debug + fmt::Display> Error for BallotValidityError<V> {
+    fn source(&self) -> Option<&(dyn Error + 'static)> {
+        None
+    }
+}
+
+impl<V: fmt::Display> fmt::Display for BallotValidityError<V> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Self::AlternativeNotFound(a) => write!(f, "{} is not a valid alternative", a),
+            Self::InvalidRankRange(a, b) => write!(f, "[{}, {}] is not a valid range", a, b),
+            Self::DuplicateAlternative(a) => write!(f, "{} appears twice in the ballot", a),
+        }
+    }
+}
+
+impl<V: fmt::Display> BallotValidityError<V> {
+    fn translate(&self, t: &i18n::Translator) -> String {
+        match self {
+            Self::AlternativeNotFound(a) => t.tr_with(
+                "error-alternative-not-found",
+                &[("alternative", a.to_string())],
+            ),
+            Self::InvalidRankRange(a, b) => t.tr_with(
+                "error-invalid-rank-range",
+                &[("min", a.to_string()), ("max", b.to_string())],
+            ),
+            Self::DuplicateAlternative(a) => t.tr_with(
+                "error-duplicate-alternative",
+                &[("alternative", a.to_string())],
+            ),
+        }
+    }
+}
+
+#[derive(Clone, Debug, Deserialize)]
+struct CredentialQuery {
+    credential: Option<String>,
+}
+
+#[derive(Clone, Debug)]
+struct AppState {
+    election_data: ElectionData,
+    database: Arc<metrics::TimedMutex<model::DatabaseConnection>>,
+    result: Option<ResultData>,
+    mailer: Option<Arc<mailer::Mailer>>,
+    webhooks: Arc<webhook::Webhooks>,
+    /// Where the configuration is written back when it is edited
+    config_path: String,
+    /// Where the uploaded files are stored
+    assets: PathBuf,
+}
+
+/// The part of the configuration read before the rest, so that everything
+/// done on start is logged. The other entries are ignored.
+#[derive(Deserialize)]
+struct LogSection {
+    #[serde(default)]
+    log: logging::LogConfig,
+}
+
+fn read_election_data(path: &str) -> Result<ElectionData, Box<dyn Error>> {
+    let file = File::open(path)?;
+    let reader = BufReader::new(file);
+    let mut election_data: ElectionData = serde_json::from_reader(reader)?;
+    election_data.theme.load()?;
+    Ok(election_data)
+}
+
+impl AppState {
+    fn new(election_config: &str, database: &str) -> Result<Self, Box<dyn Error>> {
+        let election_data = read_election_data(election_config)?;
+        // Created first, or the files would be served from the working
+        // directory
+        let assets = assets::directory(database);
+        std::fs::create_dir_all(&assets)?;
+        let connection = model::DatabaseConnection::new(
+            database,
+            &election_data.alternatives,
+            election_data.anonymous,
+        )?;
+        let mailer = match &election_data.mail {
+            Some(config) => Some(Arc::new(mailer::Mailer::new(config, database)?)),
+            None => None,
+        };
+        let database = Arc::new(metrics::TimedMutex::new(
+            connection,
+            &metrics::DATABASE_LOCK_WAIT,
+        ));
+        let webhooks = webhook::Webhooks::new(
+            &election_data.webhooks,
+            &election_data.ballot_milestones,
+            database.clone(),
+        );
+        // A closed election stays closed across restarts
+        let result = match database.lock() {
+            Ok(database_lock) => model::get_final_result(&*database_lock)?,
+            Err(what) => return Err(format!("Mutex poisoned: {}", what).into()),
+        };
+        Ok(Self {
+            election_data: election_data,
+            database: database,
+            result: result,
+            mailer: mailer,
+            webhooks: Arc::new(webhooks),
+            config_path: election_config.to_string(),
+            assets: assets,
+        })
+    }
+
+    fn is_open(&self) -> bool {
+        self.result.is_none()
+    }
+
+    fn get_title(&self) -> &str {
+        &self.election_data.title
+    }
+}
+
+/// Every edit is written to the configuration file or to the data base before
+/// the state is, so both are read again
+impl recovery::Recover for AppState {
+    fn recover(&mut self) -> Result<(), String> {
+        self.election_data = read_election_data(&self.config_path)
+            .map_err(|what| format!("Failed to read configuration: {}", what))?;
+        let database_lock = self.database.lock()?;
+        self.result = model::get_final_result(&*database_lock)
+            .map_err(|what| format!("Failed to read the result: {}", what))?;
+        Ok(())
+    }
+}
+
+type SharedState = web::Data<Arc<metrics::TimedRwLock<AppState>>>;
+
+type SharedCatalogs = web::Data<Arc<i18n::Catalogs>>;
+
+type SharedAdmin = web::Data<Arc<admin::Admin>>;
+
+fn get_ballot_secret(req: &HttpRequest) -> Option<&str> {
+    req.headers()
+        .get(BALLOT_SECRET_HEADER)
+        .and_then(|value| value.to_str().ok())
+}
+
+/// Voter credential of a request. Pages are reached through personal links,
+/// which carry it in the query.
+fn get_credential(headers: &header::HeaderMap, query: &str) -> Option<String> {
+    let query = web::Query::<CredentialQuery>::from_query(query).ok();
+    headers
+        .get(CREDENTIAL_HEADER)
+        .and_then(|value| value.to_str().ok())
+        .map(|credential| credential.to_string())
+        .or_else(|| query.and_then(|q| q.into_inner().credential))
+}
+
+/// Identifies the elector behind a request: by the credential issued from the
+/// voter roll if the election has one, by IP address otherwise.
+fn get_identity(
+    req: &HttpRequest,
+    voter_roll: bool,
+    t: &i18n::Translator,
+) -> Result<model::Identity, HttpResponse> {
+    if voter_roll {
+        match get_credential(req.headers(), req.query_string()) {
+            Some(credential) => Ok(model::Identity::Credential(credential)),
+            None => Err(HttpResponse::Unauthorized().body(t.tr("error-missing-credential"))),
+        }
+    } else {
+        match req.peer_addr() {
+            Some(a) => Ok(model::Identity::Ip(a.ip().to_string())),
+            None => {
+                Err(HttpResponse::InternalServerError()
+                    .body("Failed to retrieve client IP address"))
+            }
+        }
+    }
+}
+
+/// Gathers the alternatives and the ballot of the elector behind the request
+fn get_election_data(
+    req: &HttpRequest,
+    state: &AppState,
+    t: &i18n::Translator,
+) -> Result<model::ElectionData, HttpResponse> {
+    let identity = get_identity(req, state.election_data.voter_roll, t)?;
+
+    let mut database_lock = match state.database.lock() {
+        Ok(l) => l,
+        Err(what) => {
+            return Err(
+                HttpResponse::InternalServerError().body(&format!("Mutex poisoned: {}", what))
+            )
+        }
+    };
+
+    let mut data = match model::get_data(&mut *database_lock, &identity, get_ballot_secret(req)) {
+        Ok(data) => data,
+        Err(what) => {
+            return Err(HttpResponse::InternalServerError()
+                .body(&format!("Failed to query data base: {}", what)))
+        }
+    };
+
+    std::mem::drop(database_lock);
+
+    data.title = Some(state.election_data.title.to_string());
+
+    Ok(data)
+}
+
+async fn get_info(
+    req: HttpRequest,
+    state: SharedState,
+    catalogs: SharedCatalogs,
+) -> impl Responder {
+    let state_lock = match state.read() {
+        Ok(l) => l,
+        Err(what) => {
+            return HttpResponse::InternalServerError().body(&format!("Mutex poisoned: {}", what))
+        }
+    };
+
+    match get_election_data(&req, &*state_lock, &catalogs.negotiate(&req)) {
+        Ok(data) => HttpResponse::Ok().json(data),
+        Err(response) => response,
+    }
+}
+
+fn check_ballot_shape(ballot: &[model::BallotRow]) -> Result<(), BallotValidityError<usize>> {
+    let mut found = HashSet::new();
+    for row in ballot {
+        // Identifiers start at 1, as SQLite row identifiers do
+        if row.alternative == 0 {
+            return Err(BallotValidityError::AlternativeNotFound(row.alternative));
+        }
+        if !found.insert(row.alternative) {
+            return Err(BallotValidityError::DuplicateAlternative(row.alternative));
+        }
+        if row.min > row.max {
+            return Err(BallotValidityError::InvalidRankRange(row.min, row.max));
+        }
+    }
+    Ok(())
+}
+
+/// Reasons why a well-formed ballot is refused
+#[derive(Clone, Debug)]
+enum CastError {
+    Closed,
+    NotEnrolled,
+    MissingSecret,
+    UnknownSecret,
+    Internal(String),
+}
+
+impl CastError {
+    fn translate(&self, t: &i18n::Translator) -> String {
+        match self {
+            Self::Closed => t.tr("error-election-closed"),
+            Self::NotEnrolled => t.tr("error-not-enrolled"),
+            Self::MissingSecret => t.tr("error-secret-required"),
+            Self::UnknownSecret => t.tr("error-unknown-secret"),
+            Self::Internal(what) => what.to_string(),
+        }
+    }
+
+    fn to_response(&self, t: &i18n::Translator) -> HttpResponse {
+        match self {
+            Self::Internal(what) => HttpResponse::InternalServerError().body(what),
+            _ => HttpResponse::Forbidden().body(self.translate(t)),
+        }
+    }
+}
+
+/// Records the ballot of the elector. Returns the ballot secret if a sealed
+/// ballot was created.
+fn cast_ballot(
+    state: &AppState,
+    identity: &model::Identity,
+    secret: Option<&str>,
+    ballot: &[model::BallotRow],
+) -> Result<Option<String>, CastError> {
+    if !state.is_open() {
+        return Err(CastError::Closed);
+    }
+
+    let mut database_lock = match state.database.lock() {
+        Ok(l) => l,
+        Err(what) => return Err(CastError::Internal(format!("Mutex poisoned: {}", what))),
+    };
+
+    match model::is_enrolled(&*database_lock, identity) {
+        Ok(true) => (),
+        Ok(false) => return Err(CastError::NotEnrolled),
+        Err(what) => {
+            return Err(CastError::Internal(format!(
+                "Failed to query data base: {}",
+                what
+            )))
+        }
+    }
+
+    let cast = if database_lock.is_anonymous() {
+        match model::cast_sealed_ballot(&mut *database_lock, identity, secret, ballot) {
+            Ok(model::SealedCast::Created(secret)) => Ok(Some(secret)),
+            Ok(model::SealedCast::Replaced) => Ok(None),
+            Ok(model::SealedCast::MissingSecret) => Err(CastError::MissingSecret),
+            Ok(model::SealedCast::UnknownSecret) => Err(CastError::UnknownSecret),
+            Err(what) => Err(CastError::Internal(format!(
+                "Failed to post ballot: {}",
+                what
+            ))),
+        }
+    } else {
+        match model::set_ballot(&mut *database_lock, identity, ballot) {
+            Ok(()) => Ok(None),
+            Err(what) => Err(CastError::Internal(format!(
+                "Failed to post ballot: {}",
+                what
+            ))),
+        }
+    };
+
+    if cast.is_ok() {
+        metrics::BALLOTS_CAST.inc();
+        if let Err(what) = model::count_ballots(&*database_lock)
+            .and_then(|count| state.webhooks.ballot_count(&*database_lock, count))
+        {
+            error!(error = %what, "Failed to notify the ballot milestones");
+        }
+    }
+    cast
+}
+
+async fn post_ballot(
+    req: HttpRequest,
+    ballot: web::Json<Vec<model::BallotRow>>,
+    state: SharedState,
+    catalogs: SharedCatalogs,
+) -> impl Responder {
+    let t = catalogs.negotiate(&req);
+
+    if let Err(what) = check_ballot_shape(&ballot) {
+        return HttpResponse::BadRequest()
+            .body(t.tr_with("error-bad-ballot", &[("reason", what.translate(&t))]));
+    }
+
+    let state_lock = match state.read() {
+        Ok(l) => l,
+        Err(what) => {
+            return HttpResponse::InternalServerError().body(&format!("Mutex poisoned: {}", what))
+        }
+    };
+    let state = &*state_lock;
+
+    let identity = match get_identity(&req, state.election_data.voter_roll, &t) {
+        Ok(i) => i,
+        Err(response) => return response,
+    };
+
+    match cast_ballot(state, &identity, get_ballot_secret(&req), &ballot) {
+        Ok(Some(secret)) => HttpResponse::Created().json(SecretData { secret: secret }),
+        Ok(None) => HttpResponse::NoContent().finish(),
+        Err(what) => what.to_response(&t),
+    }
+}
+
+async fn delete_ballot(
+    req: HttpRequest,
+    state: SharedState,
+    catalogs: SharedCatalogs,
+) -> impl Responder {
+    let t = catalogs.negotiate(&req);
+
+    let state_lock = match state.read() {
+        Ok(l) => l,
+        Err(what) => {
+            return HttpResponse::InternalServerError().body(&format!("Mutex poisoned: {}", what))
+        }
+    };
+    let state = &*state_lock;
+
+    let identity = match get_identity(&req, state.election_data.voter_roll, &t) {
+        Ok(i) => i,
+        Err(response) => return response,
+    };
+
+    if state.is_open() {
+        let mut database_lock = match state.database.lock() {
+            Ok(l) => l,
+            Err(what) => {
+                return HttpResponse::InternalServerError()
+                    .body(&format!("Mutex poisoned: {}", what))
+            }
+        };
+
+        if database_lock.is_anonymous() {
+            let secret = match get_ballot_secret(&req) {
+                Some(s) => s,
+                None => return HttpResponse::Forbidden().body(t.tr("error-missing-secret")),
+            };
+            return match model::clear_sealed_ballot(&mut *database_lock, secret) {
+                Ok(true) => {
+                    metrics::BALLOTS_DELETED.inc();
+                    HttpResponse::NoContent().finish()
+                }
+                Ok(false) => HttpResponse::NotFound().body(t.tr("error-no-ballot")),
+                Err(what) => HttpResponse::InternalServerError()
+                    .body(&format!("Failed to delete ballot: {}", what)),
+            };
+        }
+
+        match model::delete_ballot(&mut *database_lock, &identity) {
+            Ok(true) => {
+                metrics::BALLOTS_DELETED.inc();
+                HttpResponse::NoContent().finish()
+            }
+            Ok(false) => HttpResponse::NotFound().body(t.tr("error-no-ballot")),
+            Err(what) => HttpResponse::InternalServerError()
+                .body(&format!("Failed to delete ballot: {}", what)),
+        }
+    } else {
+        HttpResponse::Forbidden().body(t.tr("error-election-closed"))
+    }
+}
+
+/// Returns the final result if the election is closed, or what it would be if
+/// it were closed now, without drawing a winner
+fn get_result(state: &metrics::TimedRwLock<AppState>) -> Result<ResultData, HttpResponse> {
+    let state_lock = match state.read() {
+        Ok(lock) => lock,
+        Err(what) => {
+            return Err(
+                HttpResponse::InternalServerError().body(&format!("Mutex poisoned: {}", what))
+            )
+        }
+    };
+    let state = &*state_lock;
+
+    if let Some(result) = &state.result {
+        return Ok(result.clone());
+    }
+
+    let mut database_lock = match state.database.lock() {
+        Ok(lock) => lock,
+        Err(what) => {
+            return Err(
+                HttpResponse::InternalServerError().body(&format!("Mutex poisoned: {}", what))
+            )
+        }
+    };
+
+    let (data, draws) = match model::collect_votes(&mut *database_lock)
+        .and_then(|data| Ok((data, model::count_draws(&*database_lock)?)))
+    {
+        Ok(data) => data,
+        Err(what) => {
+            return Err(HttpResponse::InternalServerError()
+                .body(&format!("Failed to collect ballots: {}", what)))
+        }
+    };
+
+    std::mem::drop(database_lock);
+
+    let mut result_data = ResultData {
+        title: state.election_data.title.to_string(),
+        alternatives: data.alternatives.to_vec(),
+        arrows: Vec::new(),
+        strategy: None,
+        seats: state.election_data.seats,
+        winner: None,
+        winners: Vec::new(),
+        draws: draws,
+    };
+
+    std::mem::drop(state_lock);
+
+    let alternatives: Vec<usize> = data.alternatives.iter().map(|x| x.id as usize).collect();
+    let graph = data.duel_graph(&alternatives);
+    for (i, alternative) in graph.get_vertices().iter().enumerate() {
+        for (j, other) in graph.get_vertices().iter().enumerate() {
+            if i != j && graph[(i, j)] {
+                result_data.arrows.push(ArrowData {
+                    from: *alternative,
+                    to: *other,
+                });
+            }
+        }
+    }
+
+    if let Some(strategy) = optimal_strategy(&graph) {
+        result_data.strategy = Some(strategy_data(&strategy));
+    }
+
+    Ok(result_data)
+}
+
+async fn result(state: SharedState) -> impl Responder {
+    match get_result(&state) {
+        Ok(result) => HttpResponse::Ok().json(result),
+        Err(response) => response,
+    }
+}
+
+/// Solves the game of the duel graph, which takes most of the time spent on a
+/// result
+fn optimal_strategy(graph: &rcvs::DuelGraph<usize>) -> Option<rcvs::Strategy<usize>> {
+    let span = tracing::debug_span!("strategy", alternatives = graph.get_vertices().len());
+    let _entered = span.enter();
+    let start = Instant::now();
+    let strategy = graph.get_optimal_strategy();
+    let elapsed = start.elapsed().as_secs_f64();
+    metrics::STRATEGY_DURATION.observe(elapsed);
+    match strategy {
+        Ok(strategy) => {
+            debug!(
+                elapsed_ms = elapsed * 1000.0,
+                "Computed the optimal strategy"
+            );
+            Some(strategy)
+        }
+        Err(what) => {
+            error!(error = %what, "Failed to compute the optimal strategy");
+            None
+        }
+    }
+}
+
+/// Fills the seats one at a time. The first winner is drawn from the optimal
+/// strategy of the whole election; each following one is drawn after removing
+/// the alternatives already elected and recomputing the duel graph.
+fn draw_winners<R: rand::Rng>(
+    data: &model::ResultData,
+    seats: usize,
+    strategy: &rcvs::Strategy<usize>,
+    rng: &mut R,
+) -> Vec<usize> {
+    let mut remaining: Vec<usize> = data.alternatives.iter().map(|x| x.id as usize).collect();
+    let mut winners = Vec::new();
+    let mut winner = strategy.play(rng);
+    while let Some(w) = winner {
+        winners.push(w);
+        remaining.retain(|&a| a != w);
+        if winners.len() >= seats || remaining.is_empty() {
+            break;
+        }
+        let graph = data.duel_graph(&remaining);
+        winner = optimal_strategy(&graph).and_then(|strategy| strategy.play(rng));
+    }
+    winners
+}
+
+/// Draws the winners again from the seed recorded when the election was
+/// closed, which gives the same winners as long as the ballots are unchanged
+pub fn replay_draw(
+    data: &model::ResultData,
+    seats: usize,
+    seed: &str,
+) -> Result<Vec<usize>, Box<dyn Error>> {
+    let mut bytes = [0u8; 32];
+    hex::decode_to_slice(seed, &mut bytes)
+        .map_err(|what| format!("Bad seed {}: {}", seed, what))?;
+    let mut rng = rand_pcg::Pcg64::from_seed(bytes);
+    let alternatives: Vec<usize> = data.alternatives.iter().map(|x| x.id as usize).collect();
+    match optimal_strategy(&data.duel_graph(&alternatives)) {
+        Some(strategy) => Ok(draw_winners(data, seats, &strategy, &mut rng)),
+        None => Err("Failed to compute the optimal strategy".into()),
+    }
+}
+
+async fn close(req: HttpRequest, state: SharedState, admin: SharedAdmin) -> impl Responder {
+    if let Err(response) = admin.authorize_change(&req, "close the election") {
+        return response;
+    }
+
+    let mut state_lock = match state.write() {
+        Ok(l) => l,
+        Err(what) => {
+            return HttpResponse::InternalServerError().body(&format!("Mutex poisoned: {}", what))
+        }
+    };
+    let state = &mut *state_lock;
+
+    // Closing again would draw other winners
+    if !state.is_open() {
+        return HttpResponse::Conflict().body("Election is already closed");
+    }
+
+    let mut database_lock = match state.database.lock() {
+        Ok(lock) => lock,
+        Err(what) => {
+            return HttpResponse::InternalServerError().body(&format!("Mutex poisoned: {}", what))
+        }
+    };
+
+    let (data, draws) = match model::collect_votes(&mut *database_lock)
+        .and_then(|data| Ok((data, model::count_draws(&*database_lock)?)))
+    {
+        Ok(data) => data,
+        Err(what) => {
+            return HttpResponse::InternalServerError()
+                .body(&format!("Failed to collect ballots: {}", what))
+        }
+    };
+
+    std::mem::drop(database_lock);
+
+    let mut result_data = ResultData {
+        title: state.election_data.title.to_string(),
+        alternatives: data.alternatives.to_vec(),
+        arrows: Vec::new(),
+        strategy: None,
+        seats: state.election_data.seats,
+        winner: None,
+        winners: Vec::new(),
+        draws: draws + 1,
+    };
+
+    // Each draw has a seed of its own, recorded so that it can be checked
+    let mut seed = [0u8; 32];
+    rand::thread_rng().fill_bytes(&mut seed);
+    let mut rng = rand_pcg::Pcg64::from_seed(seed);
+
+    let alternatives: Vec<usize> = data.alternatives.iter().map(|x| x.id as usize).collect();
+    let graph = data.duel_graph(&alternatives);
+    for (i, alternative) in graph.get_vertices().iter().enumerate() {
+        for (j, other) in graph.get_vertices().iter().enumerate() {
+            if i != j && graph[(i, j)] {
+                result_data.arrows.push(ArrowData {
+                    from: *alternative,
+                    to: *other,
+                });
+            }
+        }
+    }
+
+    if let Some(strategy) = optimal_strategy(&graph) {
+        result_data.strategy = Some(strategy_data(&strategy));
+        result_data.winners = draw_winners(&data, state.election_data.seats, &strategy, &mut rng);
+        result_data.winner = result_data.winners.first().cloned();
+    }
+
+    // The election only closes once the draw is recorded
+    let recorded = match state.database.lock() {
+        Ok(mut database_lock) => {
+            model::record_draw(&mut *database_lock, &hex::encode(seed), &result_data)
+        }
+        Err(what) => {
+            return HttpResponse::InternalServerError().body(&format!("Mutex poisoned: {}", what))
+        }
+    };
+    if let Err(what) = recorded {
+        return HttpResponse::InternalServerError()
+            .body(&format!("Failed to record draw: {}", what));
+    }
+
+    announce_result(state, &result_data);
+    match serde_json::to_value(&result_data) {
+        Ok(data) => state.webhooks.notify("election_closed", data),
+        Err(what) => error!(error = %what, "Failed to serialize result"),
+    }
+    info!(winners = ?result_data.winners, seed = %hex::encode(seed), "Election closed");
+    state.result = Some(result_data);
+    mem::drop(state_lock);
+
+    HttpResponse::NoContent().finish()
+}
+
+/// Mails the outcome to the voter roll without delaying the response
+fn announce_result(state: &AppState, result: &ResultData) {
+    let mailer = match &state.mailer {
+        Some(m) => m.clone(),
+        None => return,
+    };
+    let voters = match state.database.lock() {
+        Ok(database_lock) => match model::get_roll_credentials(&*database_lock, false) {
+            Ok(voters) => voters,
+            Err(what) => return error!(error = %what, "Failed to query data base"),
+        },
+        Err(what) => return error!(error = %what, "Mutex poisoned"),
+    };
+    let winners = result.get_winner_names();
+    let title = result.title.to_string();
+    actix_rt::spawn(
+        async move {
+            let report = web::block(move || {
+                Ok::<_, ()>(mailer.send_announcement(&title, &voters, &winners))
+            })
+            .await;
+            match report {
+                Ok(report) => info!(
+                    sent = report.sent,
+                    failed = report.failed.len(),
+                    "Sent the result announcement"
+                ),
+                Err(what) => error!(error = %what, "Failed to send result announcement"),
+            }
+        }
+        .in_current_span(),
+    );
+}
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+enum Mailing {
+    Invitation,
+    Reminder,
+}
+
+/// Collects the recipients of a mailing, then sends it on a blocking thread
+async fn send_mailing(
+    state: &metrics::TimedRwLock<AppState>,
+    mailing: Mailing,
+) -> Result<mailer::MailReport, HttpResponse> {
+    let (mailer, title, voters) =
+        {
+            let state_lock = match state.read() {
+                Ok(l) => l,
+                Err(what) => {
+                    return Err(HttpResponse::InternalServerError()
+                        .body(&format!("Mutex poisoned: {}", what)))
+                }
+            };
+            let state = &*state_lock;
+
+            let mailer = match &state.mailer {
+                Some(m) => m.clone(),
+                None => return Err(HttpResponse::NotFound().body("Mail is not configured")),
+            };
+            if mailing == Mailing::Reminder && !state.is_open() {
+                return Err(HttpResponse::Forbidden().body("Election is closed"));
+            }
+
+            let database_lock = match state.database.lock() {
+                Ok(l) => l,
+                Err(what) => {
+                    return Err(HttpResponse::InternalServerError()
+                        .body(&format!("Mutex poisoned: {}", what)))
+                }
+            };
+
+            let voters =
+                match model::get_roll_credentials(&*database_lock, mailing == Mailing::Reminder) {
+                    Ok(voters) => voters,
+                    Err(what) => {
+                        return Err(HttpResponse::InternalServerError()
+                            .body(&format!("Failed to query data base: {}", what)))
+                    }
+                };
+            (mailer, state.election_data.title.to_string(), voters)
+        };
+
+    let report = web::block(move || {
+        Ok::<_, ()>(match mailing {
+            Mailing::Invitation => mailer.send_invitations(&title, &voters),
+            Mailing::Reminder => mailer.send_reminders(&title, &voters),
+        })
+    })
+    .await;
+    report.map_err(|what| {
+        HttpResponse::InternalServerError().body(&format!("Failed to send emails: {}", what))
+    })
+}
+
+async fn mail_roll(
+    req: HttpRequest,
+    state: SharedState,
+    admin: SharedAdmin,
+    mailing: Mailing,
+) -> HttpResponse {
+    if let Err(response) = admin.authorize_change(&req, "send emails") {
+        return response;
+    }
+
+    match send_mailing(&state, mailing).await {
+        Ok(report) => HttpResponse::Ok().json(report),
+        Err(response) => response,
+    }
+}
+
+async fn send_invitations(
+    req: HttpRequest,
+    state: SharedState,
+    admin: SharedAdmin,
+) -> impl Responder {
+    mail_roll(req, state, admin, Mailing::Invitation).await
+}
+
+async fn send_reminders(
+    req: HttpRequest,
+    state: SharedState,
+    admin: SharedAdmin,
+) -> impl Responder {
+    mail_roll(req, state, admin, Mailing::Reminder).await
+}
+
+/// Key of the setting holding the time of the last reminder sent, so that a
+/// restart does not send it again
+const REMINDER_SENT: &str = "reminder_sent";
+
+/// Reads the time the reminders were last sent for or, with `value`, records it
+fn reminder_marker(
+    state: &metrics::TimedRwLock<AppState>,
+    value: Option<&str>,
+) -> Result<Option<String>, String> {
+    let state_lock = state.read().map_err(|what| what.to_string())?;
+    let database_lock = state_lock.database.lock()?;
+    match value {
+        Some(value) => model::set_setting(&*database_lock, REMINDER_SENT, value)
+            .map(|()| Some(value.to_string())),
+        None => model::get_setting(&*database_lock, REMINDER_SENT),
+    }
+    .map_err(|what| what.to_string())
+}
+
+/// Reminds the voters who have not voted yet at the configured time, unless
+/// they were already reminded for that time
+async fn remind_at(state: Arc<metrics::TimedRwLock<AppState>>, at: u64) {
+    let now = SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|d| d.as_secs())
+        .unwrap_or(0);
+    actix_rt::time::delay_for(Duration::from_secs(at.saturating_sub(now))).await;
+    match reminder_marker(&state, None) {
+        Ok(Some(sent)) if sent == at.to_string() => {
+            info!(at = at, "The reminders were already sent");
+            return;
+        }
+        Ok(_) => (),
+        Err(what) => {
+            error!(error = %what, "Failed to read whether the reminders were sent");
+            return;
+        }
+    }
+    match send_mailing(&state, Mailing::Reminder).await {
+        Ok(report) => {
+            info!(
+                sent = report.sent,
+                failed = report.failed.len(),
+                "Sent the reminders"
+            );
+            if let Err(what) = reminder_marker(&state, Some(&at.to_string())) {
+                error!(error = %what, "Failed to record that the reminders were sent");
+            }
+        }
+        Err(response) => error!(
+            status = response.status().as_u16(),
+            "Failed to send reminders"
+        ),
+    }
+}
+
+async fn open(req: HttpRequest, state: SharedState, admin: SharedAdmin) -> impl Responder {
+    if let Err(response) = admin.authorize_change(&req, "open the election") {
+        return response;
+    }
+
+    let mut state_lock = match state.write() {
+        Ok(l) => l,
+        Err(what) => {
+            return HttpResponse::InternalServerError().body(&format!("Mutex poisoned: {}", what))
+        }
+    };
+    let state = &mut *state_lock;
+
+    if state.is_open() {
+        return HttpResponse::Conflict().body("Election is already open");
+    }
+    if !state.election_data.allow_reopen {
+        return HttpResponse::Conflict()
+            .body("Election is closed for good; allow_reopen is not set in its configuration");
+    }
+
+    // Every draw made so far stays on record
+    let recorded = match state.database.lock() {
+        Ok(mut database_lock) => model::record_reopening(&mut *database_lock),
+        Err(what) => {
+            return HttpResponse::InternalServerError().body(&format!("Mutex poisoned: {}", what))
+        }
+    };
+    if let Err(what) = recorded {
+        return HttpResponse::InternalServerError()
+            .body(&format!("Failed to record reopening: {}", what));
+    }
+
+    state.result = None;
+    state.webhooks.notify(
+        "election_opened",
+        serde_json::json!({ "title": state.election_data.title }),
+    );
+    mem::drop(state_lock);
+    info!("Election reopened");
+
+    HttpResponse::NoContent().finish()
+}
+
+async fn set_weight(
+    req: HttpRequest,
+    elector: web::Path<String>,
+    weight: web::Json<WeightData>,
+    state: SharedState,
+    admin: SharedAdmin,
+) -> impl Responder {
+    if let Err(response) = admin.authorize_change(&req, "set elector weights") {
+        return response;
+    }
+
+    let elector: std::net::IpAddr = match elector.parse() {
+        Ok(a) => a,
+        Err(what) => {
+            return HttpResponse::BadRequest().body(&format!("Bad elector address: {}", what))
+        }
+    };
+    if let Err(what) = model::check_weight(weight.weight) {
+        return HttpResponse::BadRequest().body(&what);
+    }
+
+    let state_lock = match state.read() {
+        Ok(l) => l,
+        Err(what) => {
+            return HttpResponse::InternalServerError().body(&format!("Mutex poisoned: {}", what))
+        }
+    };
+    let state = &*state_lock;
+
+    // Enrolled voters are known by their credential, not by their address
+    if state.election_data.voter_roll {
+        return HttpResponse::Conflict()
+            .body("The election has a voter roll; set weights in its weight column");
+    }
+
+    let mut database_lock = match state.database.lock() {
+        Ok(l) => l,
+        Err(what) => {
+            return HttpResponse::InternalServerError().body(&format!("Mutex poisoned: {}", what))
+        }
+    };
+
+    let identity = model::Identity::Ip(elector.to_string());
+    match model::set_elector_weight(&mut *database_lock, &identity, weight.weight) {
+        Ok(true) => HttpResponse::NoContent().finish(),
+        Ok(false) => HttpResponse::Conflict()
+            .body("Elector already cast an anonymous ballot with its former weight"),
+        Err(what) => {
+            HttpResponse::InternalServerError().body(&format!("Failed to set weight: {}", what))
+        }
+    }
+}
+
+async fn import_roll(
+    req: HttpRequest,
+    body: String,
+    state: SharedState,
+    admin: SharedAdmin,
+) -> impl Responder {
+    if let Err(response) = admin.authorize_change(&req, "import the voter roll") {
+        return response;
+    }
+
+    let mut roll = Vec::new();
+    for entry in csv::Reader::from_reader(body.as_bytes()).deserialize::<model::RollEntry>() {
+        let checked = entry.map_err(|what| what.to_string()).and_then(|entry| {
+            entry.weight.map_or(Ok(1.0), model::check_weight)?;
+            Ok(entry)
+        });
+        match checked {
+            Ok(entry) => roll.push(entry),
+            Err(what) => {
+                return HttpResponse::BadRequest().body(&format!("Bad voter roll: {}", what))
+            }
+        }
+    }
+
+    let state_lock = match state.read() {
+        Ok(l) => l,
+        Err(what) => {
+            return HttpResponse::InternalServerError().body(&format!("Mutex poisoned: {}", what))
+        }
+    };
+    let state = &*state_lock;
+
+    let mut database_lock = match state.database.lock() {
+        Ok(l) => l,
+        Err(what) => {
+            return HttpResponse::InternalServerError().body(&format!("Mutex poisoned: {}", what))
+        }
+    };
+
+    match model::import_roll(&mut *database_lock, &roll) {
+        Ok(credentials) => HttpResponse::Ok().json(credentials),
+        Err(what) => HttpResponse::InternalServerError()
+            .body(&format!("Failed to import voter roll: {}", what)),
+    }
+}
+
+async fn get_roll(req: HttpRequest, state: SharedState, admin: SharedAdmin) -> impl Responder {
+    if let Err(response) = admin.authorize(&req, "read the voter roll") {
+        return response;
+    }
+
+    let state_lock = match state.read() {
+        Ok(l) => l,
+        Err(what) => {
+            return HttpResponse::InternalServerError().body(&format!("Mutex poisoned: {}", what))
+        }
+    };
+    let state = &*state_lock;
+
+    let database_lock = match state.database.lock() {
+        Ok(l) => l,
+        Err(what) => {
+            return HttpResponse::InternalServerError().body(&format!("Mutex poisoned: {}", what))
+        }
+    };
+
+    match model::get_roll(&*database_lock) {
+        Ok(roll) => HttpResponse::Ok().json(roll),
+        Err(what) => HttpResponse::InternalServerError()
+            .body(&format!("Failed to query data base: {}", what)),
+    }
+}
+
+async fn get_deliveries(
+    req: HttpRequest,
+    state: SharedState,
+    admin: SharedAdmin,
+) -> impl Responder {
+    if let Err(response) = admin.authorize(&req, "read the webhook deliveries") {
+        return response;
+    }
+
+    let state_lock = match state.read() {
+        Ok(l) => l,
+        Err(what) => {
+            return HttpResponse::InternalServerError().body(&format!("Mutex poisoned: {}", what))
+        }
+    };
+    let state = &*state_lock;
+
+    let database_lock = match state.database.lock() {
+        Ok(l) => l,
+        Err(what) => {
+            return HttpResponse::InternalServerError().body(&format!("Mutex poisoned: {}", what))
+        }
+    };
+
+    match model::get_deliveries(&*database_lock) {
+        Ok(deliveries) => HttpResponse::Ok().json(deliveries),
+        Err(what) => HttpResponse::InternalServerError()
+            .body(&format!("Failed to query data base: {}", what)),
+    }
+}
+
+/// Stores the image in the body as the icon of the alternative
+async fn upload_icon(
+    req: HttpRequest,
+    alternative: web::Path<i64>,
+    body: web::Bytes,
+    state: SharedState,
+    admin: SharedAdmin,
+) -> impl Responder {
+    if let Err(response) = admin.authorize_change(&req, "upload icons") {
+        return response;
+    }
+
+    let directory = match state.read() {
+        Ok(l) => l.assets.clone(),
+        Err(what) => {
+            return HttpResponse::InternalServerError().body(&format!("Mutex poisoned: {}", what))
+        }
+    };
+    let alternative = alternative.into_inner();
+    let icon = match web::block(move || assets::store_icon(&directory, alternative, &body)).await {
+        Ok(icon) => icon,
+        Err(BlockingError::Error(assets::IconError::UnsupportedType)) => {
+            return HttpResponse::UnsupportedMediaType()
+                .body(&assets::IconError::UnsupportedType.to_string())
+        }
+        Err(BlockingError::Error(assets::IconError::Io(what))) => {
+            return HttpResponse::InternalServerError()
+                .body(&assets::IconError::Io(what).to_string())
+        }
+        Err(BlockingError::Error(what)) => {
+            return HttpResponse::BadRequest().body(&what.to_string())
+        }
+        Err(BlockingError::Canceled) => {
+            return HttpResponse::InternalServerError().body("Image processing was canceled")
+        }
+    };
+
+    let state_lock = match state.read() {
+        Ok(l) => l,
+        Err(what) => {
+            return HttpResponse::InternalServerError().body(&format!("Mutex poisoned: {}", what))
+        }
+    };
+    let state = &*state_lock;
+
+    let mut database_lock = match state.database.lock() {
+        Ok(l) => l,
+        Err(what) => {
+            return HttpResponse::InternalServerError().body(&format!("Mutex poisoned: {}", what))
+        }
+    };
+
+    match model::set_alternative_icon(&mut *database_lock, alternative, &icon) {
+        Ok(Some(former)) => {
+            if former != icon {
+                assets::remove_icon(&state.assets, &former);
+            }
+            HttpResponse::Ok().json(IconData { icon: icon })
+        }
+        Ok(None) => {
+            assets::remove_icon(&state.assets, &icon);
+            HttpResponse::NotFound().body("No such alternative")
+        }
+        Err(what) => {
+            HttpResponse::InternalServerError().body(&format!("Failed to set icon: {}", what))
+        }
+    }
+}
+
+/// Appends to the audit log an event which is not part of a change to the
+/// data base. Failing to do so does not undo the action.
+fn log_event(state: &AppState, event: &str, data: serde_json::Value) {
+    match state.database.lock() {
+        Ok(mut database_lock) => {
+            if let Err(what) = model::log_event(&mut *database_lock, event, &data) {
+                error!(error = %what, event = event, "Failed to write to the audit log");
+            }
+        }
+        Err(what) => error!(error = %what, "Mutex poisoned"),
+    }
+}
+
+/// Writes one entry of the configuration file, keeping the others
+fn save_config_entry(
+    path: &str,
+    key: &str,
+    value: serde_json::Value,
+) -> Result<(), Box<dyn Error>> {
+    let mut config: serde_json::Map<String, serde_json::Value> =
+        serde_json::from_reader(BufReader::new(File::open(path)?))?;
+    config.insert(key.to_string(), value);
+    std::fs::write(path, serde_json::to_string_pretty(&config)? + "\n")?;
+    Ok(())
+}
+
+/// Renames the election. The configuration file is updated as well, so that
+/// the title is kept across restarts.
+async fn set_title(
+    req: HttpRequest,
+    data: web::Json<TitleData>,
+    state: SharedState,
+    admin: SharedAdmin,
+) -> impl Responder {
+    if let Err(response) = admin.authorize_change(&req, "rename the election") {
+        return response;
+    }
+
+    let title = data.title.trim();
+    if title.is_empty() {
+        return HttpResponse::BadRequest().body("The title cannot be empty");
+    }
+
+    let mut state_lock = match state.write() {
+        Ok(l) => l,
+        Err(what) => {
+            return HttpResponse::InternalServerError().body(&format!("Mutex poisoned: {}", what))
+        }
+    };
+    let state = &mut *state_lock;
+
+    if !state.is_open() {
+        return HttpResponse::Conflict().body("Election is closed");
+    }
+    if let Err(what) = save_config_entry(&state.config_path, "title", title.into()) {
+        return HttpResponse::InternalServerError()
+            .body(&format!("Failed to write configuration: {}", what));
+    }
+    state.election_data.title = title.to_string();
+    log_event(
+        state,
+        "config_changed",
+        serde_json::json!({ "title": title }),
+    );
+
+    HttpResponse::NoContent().finish()
+}
+
+/// Refuses a name that is empty or already taken by another alternative
+fn check_alternative_name(
+    alternatives: &[model::AlternativeData],
+    name: &str,
+    id: Option<i64>,
+) -> Result<(), HttpResponse> {
+    if name.is_empty() {
+        return Err(HttpResponse::BadRequest().body("The name cannot be empty"));
+    }
+    match alternatives
+        .iter()
+        .find(|a| a.name == name && Some(a.id) != id)
+    {
+        Some(_) => {
+            Err(HttpResponse::Conflict().body(&format!("An alternative is already named {}", name)))
+        }
+        None => Ok(()),
+    }
+}
+
+/// Notifies the webhooks with the alternatives as they now are
+fn announce_alternatives(state: &AppState, alternatives: &[model::AlternativeData]) {
+    match serde_json::to_value(alternatives) {
+        Ok(data) => state.webhooks.notify(
+            "alternatives_changed",
+            serde_json::json!({ "alternatives": data }),
+        ),
+        Err(what) => error!(error = %what, "Failed to serialize alternatives"),
+    }
+}
+
+async fn add_alternative(
+    req: HttpRequest,
+    data: web::Json<AlternativeEdit>,
+    state: SharedState,
+    admin: SharedAdmin,
+) -> impl Responder {
+    if let Err(response) = admin.authorize_change(&req, "edit the alternatives") {
+        return response;
+    }
+
+    let state_lock = match state.read() {
+        Ok(l) => l,
+        Err(what) => {
+            return HttpResponse::InternalServerError().body(&format!("Mutex poisoned: {}", what))
+        }
+    };
+    let state = &*state_lock;
+
+    if !state.is_open() {
+        return HttpResponse::Conflict().body("Election is closed");
+    }
+
+    let mut database_lock = match state.database.lock() {
+        Ok(l) => l,
+        Err(what) => {
+            return HttpResponse::InternalServerError().body(&format!("Mutex poisoned: {}", what))
+        }
+    };
+
+    let name = data.name.trim();
+    let alternatives = match model::get_alternatives(&mut *database_lock) {
+        Ok(alternatives) => alternatives,
+        Err(what) => {
+            return HttpResponse::InternalServerError()
+                .body(&format!("Failed to query data base: {}", what))
+        }
+    };
+    if let Err(response) = check_alternative_name(&alternatives, name, None) {
+        return response;
+    }
+
+    let id = match model::add_alternative(&mut *database_lock, name, &data.description) {
+        Ok(id) => id,
+        Err(what) => {
+            return HttpResponse::InternalServerError()
+                .body(&format!("Failed to add alternative: {}", what))
+        }
+    };
+    let alternatives = match model::get_alternatives(&mut *database_lock) {
+        Ok(alternatives) => alternatives,
+        Err(what) => {
+            return HttpResponse::InternalServerError()
+                .body(&format!("Failed to query data base: {}", what))
+        }
+    };
+
+    announce_alternatives(state, &alternatives);
+    match alternatives.into_iter().find(|a| a.id == id) {
+        Some(alternative) => HttpResponse::Created().json(alternative),
+        None => HttpResponse::InternalServerError().body("Added alternative vanished"),
+    }
+}
+
+async fn edit_alternative(
+    req: HttpRequest,
+    alternative: web::Path<i64>,
+    data: web::Json<AlternativeEdit>,
+    state: SharedState,
+    admin: SharedAdmin,
+) -> impl Responder {
+    if let Err(response) = admin.authorize_change(&req, "edit the alternatives") {
+        return response;
+    }
+
+    let state_lock = match state.read() {
+        Ok(l) => l,
+        Err(what) => {
+            return HttpResponse::InternalServerError().body(&format!("Mutex poisoned: {}", what))
+        }
+    };
+    let state = &*state_lock;
+
+    if !state.is_open() {
+        return HttpResponse::Conflict().body("Election is closed");
+    }
+
+    let mut database_lock = match state.database.lock() {
+        Ok(l) => l,
+        Err(what) => {
+            return HttpResponse::InternalServerError().body(&format!("Mutex poisoned: {}", what))
+        }
+    };
+
+    let name = data.name.trim();
+    let alternatives = match model::get_alternatives(&mut *database_lock) {
+        Ok(alternatives) => alternatives,
+        Err(what) => {
+            return HttpResponse::InternalServerError()
+                .body(&format!("Failed to query data base: {}", what))
+        }
+    };
+    if let Err(response) = check_alternative_name(&alternatives, name, Some(*alternative)) {
+        return response;
+    }
+
+    match model::update_alternative(&mut *database_lock, *alternative, name, &data.description) {
+        Ok(true) => (),
+        Ok(false) => return HttpResponse::NotFound().body("No such alternative"),
+        Err(what) => {
+            return HttpResponse::InternalServerError()
+                .body(&format!("Failed to edit alternative: {}", what))
+        }
+    }
+    match model::get_alternatives(&mut *database_lock) {
+        Ok(alternatives) => announce_alternatives(state, &alternatives),
+        Err(what) => error!(error = %what, "Failed to query data base"),
+    }
+
+    HttpResponse::NoContent().finish()
+}
+
+/// Removes an alternative along with the ranks it was given
+async fn remove_alternative(
+    req: HttpRequest,
+    alternative: web::Path<i64>,
+    state: SharedState,
+    admin: SharedAdmin,
+) -> impl Responder {
+    if let Err(response) = admin.authorize_change(&req, "edit the alternatives") {
+        return response;
+    }
+
+    let state_lock = match state.read() {
+        Ok(l) => l,
+        Err(what) => {
+            return HttpResponse::InternalServerError().body(&format!("Mutex poisoned: {}", what))
+        }
+    };
+    let state = &*state_lock;
+
+    if !state.is_open() {
+        return HttpResponse::Conflict().body("Election is closed");
+    }
+
+    let mut database_lock = match state.database.lock() {
+        Ok(l) => l,
+        Err(what) => {
+            return HttpResponse::InternalServerError().body(&format!("Mutex poisoned: {}", what))
+        }
+    };
+
+    match model::remove_alternative(&mut *database_lock, *alternative) {
+        Ok(Some(icon)) => assets::remove_icon(&state.assets, &icon),
+        Ok(None) => return HttpResponse::NotFound().body("No such alternative"),
+        Err(what) => {
+            return HttpResponse::InternalServerError()
+                .body(&format!("Failed to remove alternative: {}", what))
+        }
+    }
+    match model::get_alternatives(&mut *database_lock) {
+        Ok(alternatives) => announce_alternatives(state, &alternatives),
+        Err(what) => error!(error = %what, "Failed to query data base"),
+    }
+
+    HttpResponse::NoContent().finish()
+}
+
+async fn get_turnout(req: HttpRequest, state: SharedState, admin: SharedAdmin) -> impl Responder {
+    if let Err(response) = admin.authorize(&req, "read the turnout") {
+        return response;
+    }
+
+    let state_lock = match state.read() {
+        Ok(l) => l,
+        Err(what) => {
+            return HttpResponse::InternalServerError().body(&format!("Mutex poisoned: {}", what))
+        }
+    };
+    let state = &*state_lock;
+
+    let mut database_lock = match state.database.lock() {
+        Ok(l) => l,
+        Err(what) => {
+            return HttpResponse::InternalServerError().body(&format!("Mutex poisoned: {}", what))
+        }
+    };
+
+    match model::get_turnout(&mut *database_lock, state.is_open()) {
+        Ok(turnout) => HttpResponse::Ok().json(turnout),
+        Err(what) => HttpResponse::InternalServerError()
+            .body(&format!("Failed to collect ballots: {}", what)),
+    }
+}
+
+/// Lists the rows of every ballot as CSV, in the format `rcvs-admin` imports
+async fn export_ballots(
+    req: HttpRequest,
+    state: SharedState,
+    admin: SharedAdmin,
+) -> impl Responder {
+    if let Err(response) = admin.authorize(&req, "export the ballots") {
+        return response;
+    }
+
+    let state_lock = match state.read() {
+        Ok(l) => l,
+        Err(what) => {
+            return HttpResponse::InternalServerError().body(&format!("Mutex poisoned: {}", what))
+        }
+    };
+    let state = &*state_lock;
+
+    let mut database_lock = match state.database.lock() {
+        Ok(l) => l,
+        Err(what) => {
+            return HttpResponse::InternalServerError().body(&format!("Mutex poisoned: {}", what))
+        }
+    };
+
+    let records = match model::export_ballots(&mut *database_lock) {
+        Ok(records) => records,
+        Err(what) => {
+            return HttpResponse::InternalServerError()
+                .body(&format!("Failed to collect ballots: {}", what))
+        }
+    };
+    std::mem::drop(database_lock);
+
+    let mut writer = csv::Writer::from_writer(Vec::new());
+    for record in &records {
+        if let Err(what) = writer.serialize(record) {
+            return HttpResponse::InternalServerError()
+                .body(&format!("Failed to write CSV: {}", what));
+        }
+    }
+    match writer.into_inner() {
+        Ok(csv) => HttpResponse::Ok()
+            .content_type("text/csv; charset=utf-8")
+            .header(
+                header::CONTENT_DISPOSITION,
+                "attachment; filename=\"ballots.csv\"",
+            )
+            .body(csv),
+        Err(what) => {
+            HttpResponse::InternalServerError().body(&format!("Failed to write CSV: {}", what))
+        }
+    }
+}
+
+fn get_audit_log(
+    state: &metrics::TimedRwLock<AppState>,
+) -> Result<Vec<model::AuditEntry>, HttpResponse> {
+    let state_lock = match state.read() {
+        Ok(l) => l,
+        Err(what) => {
+            return Err(
+                HttpResponse::InternalServerError().body(&format!("Mutex poisoned: {}", what))
+            )
+        }
+    };
+    let database_lock = match state_lock.database.lock() {
+        Ok(l) => l,
+        Err(what) => {
+            return Err(
+                HttpResponse::InternalServerError().body(&format!("Mutex poisoned: {}", what))
+            )
+        }
+    };
+    model::get_audit_log(&*database_lock).map_err(|what| {
+        HttpResponse::InternalServerError().body(&format!("Failed to query data base: {}", what))
+    })
+}
+
+async fn get_audit(req: HttpRequest, state: SharedState, admin: SharedAdmin) -> impl Responder {
+    if let Err(response) = admin.authorize(&req, "read the audit log") {
+        return response;
+    }
+    match get_audit_log(&state) {
+        Ok(entries) => HttpResponse::Ok().json(entries),
+        Err(response) => response,
+    }
+}
+
+/// Writes the audit log as JSON Lines, one entry per line, so that later
+/// exports only add lines to earlier ones
+async fn export_audit(req: HttpRequest, state: SharedState, admin: SharedAdmin) -> impl Responder {
+    if let Err(response) = admin.authorize(&req, "export the audit log") {
+        return response;
+    }
+    let entries = match get_audit_log(&state) {
+        Ok(entries) => entries,
+        Err(response) => return response,
+    };
+    let mut lines = String::new();
+    for entry in &entries {
+        match serde_json::to_string(entry) {
+            Ok(line) => lines += &(line + "\n"),
+            Err(what) => {
+                return HttpResponse::InternalServerError()
+                    .body(&format!("Failed to serialize audit log: {}", what))
+            }
+        }
+    }
+    HttpResponse::Ok()
+        .content_type("application/x-ndjson")
+        .header(
+            header::CONTENT_DISPOSITION,
+            "attachment; filename=\"audit.jsonl\"",
+        )
+        .body(lines)
+}
+
+/// Metrics in the text format of Prometheus. The gauges are read from the data
+/// base, which `rcvs-admin` may have changed.
+async fn get_metrics(req: HttpRequest, state: SharedState, admin: SharedAdmin) -> impl Responder {
+    let state_lock = match state.read() {
+        Ok(l) => l,
+        Err(what) => {
+            return HttpResponse::InternalServerError().body(&format!("Mutex poisoned: {}", what))
+        }
+    };
+    // Otherwise only administrators may read them, as they tell the turnout
+    if !state_lock.election_data.metrics.is_scraper(&req) {
+        if let Err(response) = admin.authorize(&req, "read the metrics") {
+            return response;
+        }
+    }
+    metrics::ELECTION_OPEN.set(state_lock.is_open() as i64);
+    let count = match state_lock.database.lock() {
+        Ok(database_lock) => model::count_ballots(&*database_lock),
+        Err(what) => {
+            return HttpResponse::InternalServerError().body(&format!("Mutex poisoned: {}", what))
+        }
+    };
+    mem::drop(state_lock);
+    match count {
+        Ok(count) => metrics::BALLOTS.set(count as i64),
+        Err(what) => {
+            return HttpResponse::InternalServerError()
+                .body(&format!("Failed to count ballots: {}", what))
+        }
+    }
+
+    match metrics::render() {
+        Ok((content_type, body)) => HttpResponse::Ok().content_type(content_type).body(body),
+        Err(what) => {
+            HttpResponse::InternalServerError().body(&format!("Failed to encode metrics: {}", what))
+        }
+    }
+}
+
+/// Panics while holding the given lock, to test that it is recovered
+#[cfg(feature = "fault-injection")]
+async fn inject_panic(
+    req: HttpRequest,
+    lock: web::Path<String>,
+    state: SharedState,
+    admin: SharedAdmin,
+) -> HttpResponse {
+    if let Err(response) = admin.authorize_change(&req, "inject a panic") {
+        return response;
+    }
+
+    match lock.as_str() {
+        "state" => {
+            let _state_lock = state.write();
+            panic!("Injected panic holding the state lock");
+        }
+        "database" => {
+            let state_lock = state.read();
+            let _database_lock = state_lock.as_ref().map(|state| state.database.lock());
+            panic!("Injected panic holding the data base lock");
+        }
+        _ => panic!("Injected panic"),
+    }
+}
+
+/// Liveness probe: answering is enough
+async fn healthz() -> impl Responder {
+    HttpResponse::Ok().json(serde_json::json!({ "status": "ok" }))
+}
+
+fn probe_check(result: Result<serde_json::Value, String>) -> (bool, serde_json::Value) {
+    match result {
+        Ok(mut detail) => {
+            if let Some(detail) = detail.as_object_mut() {
+                detail.insert("ok".to_string(), true.into());
+            }
+            (true, detail)
+        }
+        Err(what) => (false, serde_json::json!({ "ok": false, "error": what })),
+    }
+}
+
+/// Readiness probe: the state and data base locks are not poisoned, the
+/// configuration is loaded and the data base answers with the expected schema.
+/// Checks that depend on a failed one are left out.
+async fn readyz(state: SharedState) -> impl Responder {
+    let mut checks = Vec::new();
+    match state.read() {
+        Err(what) => checks.push(("state", Err(format!("Mutex poisoned: {}", what)))),
+        Ok(state_lock) => {
+            checks.push(("state", Ok(serde_json::json!({}))));
+            checks.push((
+                "config",
+                Ok(serde_json::json!({
+                    "title": state_lock.get_title(),
+                    "alternatives": state_lock.election_data.alternatives.len(),
+                })),
+            ));
+            match state_lock.database.lock() {
+                Err(what) => checks.push(("database", Err(format!("Mutex poisoned: {}", what)))),
+                Ok(database_lock) => match model::schema_version(&*database_lock) {
+                    Err(what) => checks.push(("database", Err(what.to_string()))),
+                    Ok(version) => {
+                        checks.push(("database", Ok(serde_json::json!({}))));
+                        checks.push((
+                            "schema",
+                            if version == model::SCHEMA_VERSION {
+                                Ok(serde_json::json!({ "version": version }))
+                            } else {
+                                Err(format!(
+                                    "Version {}, expected {}",
+                                    version,
+                                    model::SCHEMA_VERSION
+                                ))
+                            },
+                        ));
+                    }
+                },
+            }
+        }
+    }
+
+    let mut ready = true;
+    let mut detail = serde_json::Map::new();
+    for (name, result) in checks {
+        let (ok, check) = probe_check(result);
+        ready &= ok;
+        detail.insert(name.to_string(), check);
+    }
+    let body = serde_json::json!({ "ready": ready, "checks": detail });
+    if ready {
+        HttpResponse::Ok().json(body)
+    } else {
+        HttpResponse::ServiceUnavailable().json(body)
+    }
+}
+
+async fn about(req: HttpRequest, state: SharedState, catalogs: SharedCatalogs) -> impl Responder {
+    let state_lock = match state.read() {
+        Ok(lock) => lock,
+        Err(what) => {
+            return HttpResponse::InternalServerError().body(&format!("Mutex poisoned: {}", what))
+        }
+    };
+    html_interface::render(&html_interface::AboutPage {
+        t: catalogs.negotiate(&req),
+        theme: &(*state_lock).election_data.theme,
+        title: (*state_lock).get_title(),
+    })
+}
+
+async fn openapi_document() -> impl Responder {
+    HttpResponse::Ok().json(openapi::document())
+}
+
+/// Standalone bundle of Redoc, pinned so that the API reference does not
+/// depend on a CDN; see `vendor/README.md` to upgrade it
+const REDOC_SCRIPT: &[u8] = include_bytes!("../vendor/redoc-2.0.0.standalone.js");
+
+async fn redoc_script() -> impl Responder {
+    HttpResponse::Ok()
+        .content_type("application/javascript")
+        .header(header::CACHE_CONTROL, "public, max-age=31536000, immutable")
+        .body(REDOC_SCRIPT)
+}
+
+async fn api_page(state: SharedState) -> impl Responder {
+    let state_lock = match state.read() {
+        Ok(l) => l,
+        Err(what) => {
+            return HttpResponse::InternalServerError().body(&format!("Mutex poisoned: {}", what))
+        }
+    };
+    html_interface::render(&html_interface::ApiPage {
+        title: (*state_lock).get_title(),
+    })
+}
+
+#[derive(Deserialize)]
+struct LoginForm {
+    password: String,
+}
+
+fn see_other(location: &str) -> actix_web::dev::HttpResponseBuilder {
+    let mut response = HttpResponse::SeeOther();
+    response.header(header::LOCATION, location);
+    response
+}
+
+async fn admin_page(
+    req: HttpRequest,
+    state: SharedState,
+    catalogs: SharedCatalogs,
+    admin: SharedAdmin,
+) -> impl Responder {
+    if admin.authorize(&req, "administrate").is_err() {
+        return see_other("/admin/login").finish();
+    }
+
+    let state_lock = match state.read() {
+        Ok(l) => l,
+        Err(what) => {
+            return HttpResponse::InternalServerError().body(&format!("Mutex poisoned: {}", what))
+        }
+    };
+    html_interface::render(&html_interface::AdminPage {
+        t: catalogs.negotiate(&req),
+        theme: &state_lock.election_data.theme,
+        title: state_lock.get_title(),
+        csrf: admin.csrf_token(&req),
+    })
+}
+
+fn render_login(
+    req: &HttpRequest,
+    state: &metrics::TimedRwLock<AppState>,
+    catalogs: &i18n::Catalogs,
+    admin: &admin::Admin,
+    error: Option<&str>,
+) -> HttpResponse {
+    let t = catalogs.negotiate(req);
+    let state_lock = match state.read() {
+        Ok(l) => l,
+        Err(what) => {
+            return HttpResponse::InternalServerError().body(&format!("Mutex poisoned: {}", what))
+        }
+    };
+    html_interface::render_with_status(
+        if error.is_some() {
+            StatusCode::FORBIDDEN
+        } else {
+            StatusCode::OK
+        },
+        &html_interface::AdminLoginPage {
+            error: error.map(|id| t.tr(id)),
+            t: t,
+            theme: &state_lock.election_data.theme,
+            title: state_lock.get_title(),
+            has_password: admin.has_password(),
+        },
+    )
+}
+
+async fn login_page(
+    req: HttpRequest,
+    state: SharedState,
+    catalogs: SharedCatalogs,
+    admin: SharedAdmin,
+) -> impl Responder {
+    render_login(&req, &state, &catalogs, &admin, None)
+}
+
+async fn login(
+    req: HttpRequest,
+    form: web::Form<LoginForm>,
+    state: SharedState,
+    catalogs: SharedCatalogs,
+    admin: SharedAdmin,
+) -> impl Responder {
+    let token = admin.login(&form.password);
+    let address = req
+        .peer_addr()
+        .map(|a| a.ip().to_string())
+        .unwrap_or_default();
+    match state.read() {
+        Ok(state_lock) => log_event(
+            &*state_lock,
+            "admin_login",
+            serde_json::json!({ "address": address, "success": token.is_some() }),
+        ),
+        Err(what) => error!(error = %what, "Mutex poisoned"),
+    }
+    match token {
+        Some(token) => see_other("/admin")
+            .cookie(admin::session_cookie(&req, &token))
+            .finish(),
+        None => render_login(
+            &req,
+            &state,
+            &catalogs,
+            &admin,
+            Some("admin-wrong-password"),
+        ),
+    }
+}
+
+async fn logout(req: HttpRequest, admin: SharedAdmin) -> impl Responder {
+    if admin::is_cross_origin(&req) {
+        return HttpResponse::Forbidden().body("Cross-origin request refused");
+    }
+    admin.logout(&req);
+    see_other("/admin/login")
+        .del_cookie(&admin::session_cookie(&req, ""))
+        .finish()
+}
+
+async fn vote_page(
+    req: HttpRequest,
+    state: SharedState,
+    catalogs: SharedCatalogs,
+) -> impl Responder {
+    let t = catalogs.negotiate(&req);
+
+    let state_lock = match state.read() {
+        Ok(lock) => lock,
+        Err(what) => {
+            return HttpResponse::InternalServerError().body(&format!("Mutex poisoned: {}", what))
+        }
+    };
+    let state = &*state_lock;
+
+    let data = match get_election_data(&req, state, &t) {
+        Ok(data) => data,
+        Err(response) => return response,
+    };
+    let election_json = match serde_json::to_string(&data) {
+        Ok(json) => json,
+        Err(what) => {
+            return HttpResponse::InternalServerError()
+                .body(&format!("Failed to serialize election data: {}", what))
+        }
+    };
+
+    html_interface::render(&html_interface::VotePage {
+        t: t,
+        theme: &state.election_data.theme,
+        title: state.get_title(),
+        election_json: election_json,
+        rows: html_interface::ballot_lines(&data.alternatives, &data.ballot),
+        anonymous: state.election_data.anonymous,
+        error: None,
+        confirmation: None,
+        secret: None,
+    })
+}
+
+/// Form flow of the ballot page for browsers without JavaScript. The page is
+/// rendered again with the submitted values and either the errors or a
+/// confirmation.
+async fn vote_form(
+    req: HttpRequest,
+    form: web::Form<HashMap<String, String>>,
+    state: SharedState,
+    catalogs: SharedCatalogs,
+) -> impl Responder {
+    let t = catalogs.negotiate(&req);
+
+    let state_lock = match state.read() {
+        Ok(lock) => lock,
+        Err(what) => {
+            return HttpResponse::InternalServerError().body(&format!("Mutex poisoned: {}", what))
+        }
+    };
+    let state = &*state_lock;
+
+    let identity = match get_identity(&req, state.election_data.voter_roll, &t) {
+        Ok(i) => i,
+        Err(response) => return response,
+    };
+
+    let mut data = match get_election_data(&req, state, &t) {
+        Ok(data) => data,
+        Err(response) => return response,
+    };
+
+    let secret = form
+        .get("secret")
+        .map(|s| s.trim())
+        .filter(|s| !s.is_empty());
+    let (rows, ballot) = html_interface::parse_ballot_form(&data.alternatives, &form, &t);
+    let (status, error, confirmation, secret) = match ballot {
+        None => (
+            StatusCode::BAD_REQUEST,
+            Some(t.tr("error-correct-ranks")),
+            None,
+            secret.map(|s| s.to_string()),
+        ),
+        Some(ballot) => match check_ballot_shape(&ballot) {
+            Err(what) => (
+                StatusCode::BAD_REQUEST,
+                Some(t.tr_with("error-bad-ballot", &[("reason", what.translate(&t))])),
+                None,
+                secret.map(|s| s.to_string()),
+            ),
+            Ok(()) => match cast_ballot(state, &identity, secret, &ballot) {
+                Ok(new_secret) => {
+                    data.ballot = ballot;
+                    (
+                        StatusCode::OK,
+                        None,
+                        Some(t.tr("ballot-sent")),
+                        new_secret.or_else(|| secret.map(|s| s.to_string())),
+                    )
+                }
+                Err(CastError::Internal(what)) => {
+                    return HttpResponse::InternalServerError().body(&what)
+                }
+                Err(what) => (
+                    StatusCode::FORBIDDEN,
+                    Some(what.translate(&t)),
+                    None,
+                    secret.map(|s| s.to_string()),
+                ),
+            },
+        },
+    };
+
+    let election_json = match serde_json::to_string(&data) {
+        Ok(json) => json,
+        Err(what) => {
+            return HttpResponse::InternalServerError()
+                .body(&format!("Failed to serialize election data: {}", what))
+        }
+    };
+
+    html_interface::render_with_status(
+        status,
+        &html_interface::VotePage {
+            t: t,
+            theme: &state.election_data.theme,
+            title: state.get_title(),
+            election_json: election_json,
+            rows: rows,
+            anonymous: state.election_data.anonymous,
+            error: error,
+            confirmation: confirmation,
+            secret: secret,
+        },
+    )
+}
+
+async fn result_page(
+    req: HttpRequest,
+    state: SharedState,
+    catalogs: SharedCatalogs,
+) -> impl Responder {
+    let result = match get_result(&state) {
+        Ok(result) => result,
+        Err(response) => return response,
+    };
+    let result_json = match serde_json::to_string(&result) {
+        Ok(json) => json,
+        Err(what) => {
+            return HttpResponse::InternalServerError()
+                .body(&format!("Failed to serialize result: {}", what))
+        }
+    };
+
+    // The result was computed under its own lock
+    let state_lock = match state.read() {
+        Ok(lock) => lock,
+        Err(what) => {
+            return HttpResponse::InternalServerError().body(&format!("Mutex poisoned: {}", what))
+        }
+    };
+
+    html_interface::render(&html_interface::ResultPage {
+        t: catalogs.negotiate(&req),
+        theme: &state_lock.election_data.theme,
+        title: &result.title,
+        result_json: result_json,
+        winner: result.winner.and_then(|w| result.get_name(w)),
+        winners: result.get_winner_names(),
+        draws: result.draws,
+    })
+}
+
+/// Server of one election. Its routes are added to an Actix `App` by
+/// `configure`, so that it can be run by `HttpServer` as well as by tests.
+#[derive(Clone)]
+pub struct Server {
+    state: Arc<metrics::TimedRwLock<AppState>>,
+    catalogs: Arc<i18n::Catalogs>,
+    admin: Arc<admin::Admin>,
+    limiter: Arc<rate_limit::RateLimiter>,
+    tls: Option<TlsConfig>,
+    assets: PathBuf,
+}
+
+impl Server {
+    /// Reads the election configuration and opens its data base, which is
+    /// created on the first run
+    pub fn new(election_config: &str, database: &str) -> Result<Self, Box<dyn Error>> {
+        let state = AppState::new(election_config, database)?;
+        Ok(Self {
+            admin: Arc::new(admin::Admin::new(state.election_data.admin.clone())),
+            limiter: Arc::new(rate_limit::RateLimiter::new(
+                &state.election_data.rate_limit,
+            )),
+            tls: state.election_data.tls.clone(),
+            assets: state.assets.clone(),
+            state: Arc::new(metrics::TimedRwLock::new(state, &metrics::STATE_LOCK_WAIT)),
+            catalogs: Arc::new(i18n::Catalogs::new()),
+        })
+    }
+
+    /// Logs as the `log` section of the configuration says. Must be called
+    /// before `new`, so that opening and migrating the data base is logged,
+    /// and once per process.
+    pub fn init_logging(election_config: &str) -> Result<(), Box<dyn Error>> {
+        let file = File::open(election_config)?;
+        let config: LogSection = serde_json::from_reader(BufReader::new(file))?;
+        logging::init(&config.log);
+        Ok(())
+    }
+
+    pub fn tls(&self) -> Option<&TlsConfig> {
+        self.tls.as_ref()
+    }
+
+    /// Strict-Transport-Security header, sent only when serving HTTPS
+    pub fn hsts(&self) -> middleware::DefaultHeaders {
+        match self.tls() {
+            Some(tls) => tls.hsts(),
+            None => middleware::DefaultHeaders::new(),
+        }
+    }
+
+    /// Sends the reminders at the time set in the mail configuration, if any.
+    /// Must be called from the Actix system.
+    pub fn schedule_reminders(&self) {
+        let reminder_at = match self.state.read() {
+            Ok(state) => state
+                .election_data
+                .mail
+                .as_ref()
+                .and_then(|m| m.reminder_at),
+            Err(_) => None,
+        };
+        if let Some(at) = reminder_at {
+            actix_rt::spawn(remind_at(self.state.clone(), at));
+        }
+    }
+
+    pub fn configure(&self, config: &mut web::ServiceConfig) {
+        config
+            .data(self.state.clone())
+            .data(self.catalogs.clone())
+            .data(self.admin.clone())
+            .service(
+                web::scope("/api")
+                    .route("/", web::get().to(get_info))
+                    .route("/openapi.json", web::get().to(openapi_document))
+                    .route("/docs", web::get().to(api_page))
+                    .route("/redoc-2.0.0.standalone.js", web::get().to(redoc_script))
+                    .service(
+                        web::resource("/ballot")
+                            .wrap(rate_limit::RateLimit(self.limiter.clone()))
+                            .app_data(
+                                web::JsonConfig::default().limit(self.limiter.max_ballot_size()),
+                            )
+                            .route(web::get().to(get_info))
+                            .route(web::post().to(post_ballot))
+                            .route(web::delete().to(delete_ballot)),
+                    )
+                    .route("/result", web::get().to(result))
+                    .route("/elector/{ip}", web::put().to(set_weight))
+                    .route("/title", web::put().to(set_title))
+                    .route("/alternatives", web::post().to(add_alternative))
+                    .route("/alternative/{id}", web::put().to(edit_alternative))
+                    .route("/alternative/{id}", web::delete().to(remove_alternative))
+                    .service(
+                        web::resource("/alternative/{id}/icon")
+                            .app_data(web::PayloadConfig::new(assets::MAX_UPLOAD_SIZE))
+                            .route(web::put().to(upload_icon)),
+                    )
+                    .route("/roll", web::get().to(get_roll))
+                    .route("/roll", web::post().to(import_roll))
+                    .route("/mail/invitations", web::post().to(send_invitations))
+                    .route("/mail/reminders", web::post().to(send_reminders))
+                    .route("/webhooks/deliveries", web::get().to(get_deliveries))
+                    .route("/turnout", web::get().to(get_turnout))
+                    .route("/export/ballots", web::get().to(export_ballots))
+                    .route("/audit", web::get().to(get_audit))
+                    .route("/export/audit", web::get().to(export_audit))
+                    .route("/close", web::post().to(close))
+                    .route("/open", web::post().to(open)),
+            )
+            // Asset names change with their contents
+            .service(
+                web::scope(assets::ASSETS_URL)
+                    .wrap(
+                        middleware::DefaultHeaders::new()
+                            .header(header::CACHE_CONTROL, "public, max-age=31536000, immutable"),
+                    )
+                    .service(actix_files::Files::new("/", &self.assets)),
+            )
+            .service(
+                web::resource("/vote")
+                    .wrap(rate_limit::RateLimit(self.limiter.clone()))
+                    .route(web::get().to(vote_page))
+                    .route(web::post().to(vote_form)),
+            )
+            .route("/result", web::get().to(result_page))
+            .route("/admin", web::get().to(admin_page))
+            .service(
+                web::resource("/admin/login")
+                    .wrap(rate_limit::RateLimit(self.limiter.clone()))
+                    .route(web::get().to(login_page))
+                    .route(web::post().to(login)),
+            )
+            .route("/admin/logout", web::post().to(logout))
+            .route("/metrics", web::get().to(get_metrics))
+            .route("/healthz", web::get().to(healthz))
+            .route("/readyz", web::get().to(readyz))
+            .route("/", web::get().to(about));
+
+        #[cfg(feature = "fault-injection")]
+        config.route("/debug/panic/{lock}", web::post().to(inject_panic));
+    }
+}
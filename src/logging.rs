@@ -0,0 +1,173 @@
+use std::future::{ready, Future, Ready};
+use std::pin::Pin;
+use std::task::{Context, Poll};
+use std::time::Instant;
+
+use actix_web::dev::{Service, ServiceRequest, ServiceResponse, Transform};
+use actix_web::http::header::{HeaderName, HeaderValue};
+use rand::RngCore;
+use serde::Deserialize;
+use tracing::Instrument;
+use tracing_subscriber::EnvFilter;
+
+/// Header carrying the identifier of a request, which is taken from the
+/// request if a proxy already set it and sent back with the response
+pub const REQUEST_ID_HEADER: &str = "x-request-id";
+
+#[derive(Deserialize, Clone, Copy, Debug, PartialEq)]
+#[serde(rename_all = "lowercase")]
+pub enum LogFormat {
+    Text,
+    /// One JSON object per line, for log collectors
+    Json,
+}
+
+#[derive(Deserialize, Clone, Debug)]
+pub struct LogConfig {
+    /// Filter in the syntax of `RUST_LOG`, such as `info` or
+    /// `rcvs_web=debug,actix_web=warn`. `RUST_LOG` takes precedence.
+    #[serde(default = "default_level")]
+    pub level: String,
+    #[serde(default = "default_format")]
+    pub format: LogFormat,
+}
+
+fn default_level() -> String {
+    "info".to_string()
+}
+
+fn default_format() -> LogFormat {
+    LogFormat::Text
+}
+
+impl Default for LogConfig {
+    fn default() -> Self {
+        Self {
+            level: default_level(),
+            format: default_format(),
+        }
+    }
+}
+
+/// Installs the global subscriber. Only the first call has an effect, so that
+/// several servers can run in one process, as tests do.
+pub fn init(config: &LogConfig) {
+    let (filter, bad_level) = match EnvFilter::try_from_default_env() {
+        Ok(filter) => (filter, None),
+        Err(_) => match EnvFilter::try_new(&config.level) {
+            Ok(filter) => (filter, None),
+            Err(what) => (EnvFilter::new(default_level()), Some(what)),
+        },
+    };
+    let builder = tracing_subscriber::fmt().with_env_filter(filter);
+    let installed = match config.format {
+        LogFormat::Text => builder.try_init(),
+        LogFormat::Json => builder.json().try_init(),
+    };
+    if let (Ok(()), Some(what)) = (installed, bad_level) {
+        tracing::warn!(level = %config.level, error = %what, "Invalid log level, using info");
+    }
+}
+
+fn make_request_id() -> String {
+    let mut bytes = [0u8; 8];
+    rand::thread_rng().fill_bytes(&mut bytes);
+    hex::encode(bytes)
+}
+
+/// Identifiers set by proxies are kept if they cannot garble the logs
+fn is_valid_request_id(id: &str) -> bool {
+    !id.is_empty()
+        && id.len() <= 64
+        && id
+            .bytes()
+            .all(|b| b.is_ascii_alphanumeric() || b == b'-' || b == b'_' || b == b'.')
+}
+
+/// Middleware running each request in a span holding its identifier, so that
+/// every event logged while serving it can be traced back to it, and logging
+/// its outcome and duration
+pub struct RequestTracing;
+
+impl<S, B> Transform<S> for RequestTracing
+where
+    S: Service<Request = ServiceRequest, Response = ServiceResponse<B>, Error = actix_web::Error>,
+    S::Future: 'static,
+    B: 'static,
+{
+    type Request = ServiceRequest;
+    type Response = ServiceResponse<B>;
+    type Error = actix_web::Error;
+    type InitError = ();
+    type Transform = RequestTracingMiddleware<S>;
+    type Future = Ready<Result<Self::Transform, Self::InitError>>;
+
+    fn new_transform(&self, service: S) -> Self::Future {
+        ready(Ok(RequestTracingMiddleware { service: service }))
+    }
+}
+
+pub struct RequestTracingMiddleware<S> {
+    service: S,
+}
+
+impl<S, B> Service for RequestTracingMiddleware<S>
+where
+    S: Service<Request = ServiceRequest, Response = ServiceResponse<B>, Error = actix_web::Error>,
+    S::Future: 'static,
+    B: 'static,
+{
+    type Request = ServiceRequest;
+    type Response = ServiceResponse<B>;
+    type Error = actix_web::Error;
+    type Future = Pin<Box<dyn Future<Output = Result<Self::Response, Self::Error>>>>;
+
+    fn poll_ready(&mut self, cx: &mut Context<'_>) -> Poll<Result<(), Self::Error>> {
+        self.service.poll_ready(cx)
+    }
+
+    fn call(&mut self, req: ServiceRequest) -> Self::Future {
+        let id = req
+            .headers()
+            .get(REQUEST_ID_HEADER)
+            .and_then(|value| value.to_str().ok())
+            .filter(|id| is_valid_request_id(id))
+            .map(|id| id.to_string())
+            .unwrap_or_else(make_request_id);
+        let span = tracing::info_span!(
+            "request",
+            id = %id,
+            method = %req.method(),
+            path = %req.path(),
+            address = %req.peer_addr().map(|a| a.ip().to_string()).unwrap_or_default(),
+        );
+        let start = Instant::now();
+        let future = span.in_scope(|| self.service.call(req));
+
+        Box::pin(
+            async move {
+                let mut response = match future.await {
+                    Ok(response) => response,
+                    Err(what) => {
+                        tracing::error!(error = %what, "Request failed");
+                        return Err(what);
+                    }
+                };
+                let status = response.status();
+                let elapsed = start.elapsed().as_secs_f64() * 1000.0;
+                if status.is_server_error() {
+                    tracing::error!(status = status.as_u16(), elapsed_ms = elapsed, "Served");
+                } else {
+                    tracing::info!(status = status.as_u16(), elapsed_ms = elapsed, "Served");
+                }
+                if let Ok(value) = HeaderValue::from_str(&id) {
+                    response
+                        .headers_mut()
+                        .insert(HeaderName::from_static(REQUEST_ID_HEADER), value);
+                }
+                Ok(response)
+            }
+            .instrument(span),
+        )
+    }
+}
@@ -0,0 +1,188 @@
+use std::path::{Path, PathBuf};
+use std::{error::Error, fmt, fs};
+
+use lettre::message::header::ContentType;
+use lettre::transport::smtp::authentication::Credentials;
+use lettre::{Message, SmtpTransport, Transport};
+use serde::Deserialize;
+use tracing::{error, warn};
+
+pub use rcvs_web_types::MailReport;
+
+use crate::model::IssuedCredential;
+
+#[derive(Deserialize, Clone, Debug)]
+pub struct MailConfig {
+    host: String,
+    #[serde(default = "default_port")]
+    port: u16,
+    #[serde(default)]
+    username: Option<String>,
+    #[serde(default)]
+    password: Option<String>,
+    /// Plain SMTP is only meant for a local relay or a test sink
+    #[serde(default = "default_tls")]
+    tls: bool,
+    from: String,
+    /// Public address of the server, used to build personal voting links
+    base_url: String,
+    #[serde(default = "default_templates")]
+    templates: String,
+    /// UNIX time at which non-voters are reminded to vote
+    #[serde(default)]
+    pub reminder_at: Option<u64>,
+}
+
+fn default_port() -> u16 {
+    587
+}
+
+fn default_tls() -> bool {
+    true
+}
+
+fn default_templates() -> String {
+    "mail".to_string()
+}
+
+/// Replaces the variables of a template in a single pass, so that a value
+/// holding the name of another variable, such as a voter named `$LINK`, is
+/// left as it is
+fn substitute(template: &str, variables: &[(&str, &str)]) -> String {
+    let mut text = String::with_capacity(template.len());
+    let mut rest = template;
+    while let Some(start) = rest.find('$') {
+        text.push_str(&rest[..start]);
+        rest = &rest[start..];
+        match variables.iter().find(|(name, _)| rest.starts_with(name)) {
+            Some((name, value)) => {
+                text.push_str(value);
+                rest = &rest[name.len()..];
+            }
+            None => {
+                text.push('$');
+                rest = &rest[1..];
+            }
+        }
+    }
+    text.push_str(rest);
+    text
+}
+
+pub struct Mailer {
+    config: MailConfig,
+    /// Directory of the templates, relative to that of the data base unless
+    /// the configuration gives an absolute path
+    templates: PathBuf,
+    transport: SmtpTransport,
+}
+
+impl fmt::Debug for Mailer {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.debug_struct("Mailer")
+            .field("config", &self.config)
+            .finish()
+    }
+}
+
+impl Mailer {
+    pub fn new(config: &MailConfig, database: &str) -> Result<Self, Box<dyn Error>> {
+        let builder = if config.tls {
+            SmtpTransport::starttls_relay(&config.host)?
+        } else {
+            SmtpTransport::builder_dangerous(&config.host)
+        };
+        let builder = match (&config.username, &config.password) {
+            (Some(username), Some(password)) => {
+                builder.credentials(Credentials::new(username.to_string(), password.to_string()))
+            }
+            _ => builder,
+        };
+        let templates = Path::new(database)
+            .parent()
+            .unwrap_or_else(|| Path::new(""))
+            .join(&config.templates);
+        Ok(Self {
+            config: config.clone(),
+            templates: templates,
+            transport: builder.port(config.port).build(),
+        })
+    }
+
+    fn link(&self, voter: &IssuedCredential) -> String {
+        format!(
+            "{}/vote?credential={}",
+            self.config.base_url.trim_end_matches('/'),
+            voter.credential
+        )
+    }
+
+    /// Sends one email per voter from the given template. A failure for one
+    /// address does not prevent sending to the others.
+    fn send_all(
+        &self,
+        template: &str,
+        voters: &[IssuedCredential],
+        variables: &[(&str, &str)],
+    ) -> MailReport {
+        let mut report = MailReport::default();
+        let template = match fs::read_to_string(self.templates.join(template)) {
+            Ok(t) => t,
+            Err(what) => {
+                error!(template = %template, error = %what, "Failed to read mail template");
+                report.failed = voters.iter().map(|v| v.email.to_string()).collect();
+                return report;
+            }
+        };
+        for voter in voters {
+            let link = self.link(voter);
+            let mut all = vec![("$NAME", voter.name.as_str()), ("$LINK", link.as_str())];
+            all.extend_from_slice(variables);
+            let text = substitute(&template, &all);
+            match self.send(&voter.email, &text) {
+                Ok(()) => report.sent += 1,
+                Err(what) => {
+                    warn!(to = %voter.email, error = %what, "Failed to send email");
+                    report.failed.push(voter.email.to_string());
+                }
+            }
+        }
+        report
+    }
+
+    /// Sends an email whose first line is `Subject: …`, followed by the body
+    fn send(&self, to: &str, text: &str) -> Result<(), Box<dyn Error>> {
+        let (head, body) = text.split_at(text.find('\n').unwrap_or_else(|| text.len()));
+        let subject = head.trim_start_matches("Subject:").trim();
+        let email = Message::builder()
+            .from(self.config.from.parse()?)
+            .to(to.parse()?)
+            .subject(subject)
+            .header(ContentType::TEXT_PLAIN)
+            .body(body.trim_start().to_string())?;
+        self.transport.send(&email)?;
+        Ok(())
+    }
+
+    pub fn send_invitations(&self, title: &str, voters: &[IssuedCredential]) -> MailReport {
+        self.send_all("invitation.txt", voters, &[("$TITLE", title)])
+    }
+
+    pub fn send_reminders(&self, title: &str, voters: &[IssuedCredential]) -> MailReport {
+        self.send_all("reminder.txt", voters, &[("$TITLE", title)])
+    }
+
+    pub fn send_announcement(
+        &self,
+        title: &str,
+        voters: &[IssuedCredential],
+        winners: &[String],
+    ) -> MailReport {
+        let winners = winners.join(", ");
+        self.send_all(
+            "announcement.txt",
+            voters,
+            &[("$TITLE", title), ("$WINNERS", &winners)],
+        )
+    }
+}
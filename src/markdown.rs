@@ -0,0 +1,50 @@
+use std::collections::HashSet;
+
+use pulldown_cmark::{html, Options, Parser};
+
+/// Elements that may appear in a description. Headings start at level 3 so
+/// that they stay below the titles of the pages.
+const TAGS: &[&str] = &[
+    "a",
+    "blockquote",
+    "br",
+    "code",
+    "del",
+    "em",
+    "h3",
+    "h4",
+    "h5",
+    "h6",
+    "hr",
+    "li",
+    "ol",
+    "p",
+    "pre",
+    "strong",
+    "table",
+    "tbody",
+    "td",
+    "th",
+    "thead",
+    "tr",
+    "ul",
+];
+
+const URL_SCHEMES: &[&str] = &["http", "https", "mailto"];
+
+/// Renders Markdown to HTML, keeping only the allowed elements and
+/// attributes. Raw HTML in the source goes through the same filter.
+pub fn to_html(text: &str) -> String {
+    let mut options = Options::empty();
+    options.insert(Options::ENABLE_TABLES);
+    options.insert(Options::ENABLE_STRIKETHROUGH);
+    let mut unsafe_html = String::new();
+    html::push_html(&mut unsafe_html, Parser::new_ext(text, options));
+
+    ammonia::Builder::new()
+        .tags(TAGS.iter().cloned().collect::<HashSet<_>>())
+        .url_schemes(URL_SCHEMES.iter().cloned().collect::<HashSet<_>>())
+        .link_rel(Some("noopener noreferrer nofollow"))
+        .clean(&unsafe_html)
+        .to_string()
+}
@@ -0,0 +1,270 @@
+use std::fmt;
+use std::future::{ready, Future, Ready};
+use std::ops::{Deref, DerefMut};
+use std::pin::Pin;
+use std::sync::{Mutex, MutexGuard, PoisonError};
+use std::task::{Context, Poll};
+use std::time::Instant;
+
+use actix_web::dev::{Service, ServiceRequest, ServiceResponse, Transform};
+use actix_web::http::header;
+use actix_web::HttpRequest;
+use lazy_static::lazy_static;
+use prometheus::{
+    register_histogram, register_histogram_vec, register_int_counter, register_int_counter_vec,
+    register_int_gauge, Encoder, Histogram, HistogramVec, IntCounter, IntCounterVec, IntGauge,
+    TextEncoder,
+};
+
+use serde::Deserialize;
+
+use crate::admin;
+use crate::recovery::Recover;
+
+/// Scrapers on other hosts have no administrator session, so they may be given
+/// a token to send as `Authorization: Bearer <token>` instead
+#[derive(Deserialize, Clone, Debug, Default)]
+pub struct MetricsConfig {
+    #[serde(default)]
+    token: Option<String>,
+}
+
+impl MetricsConfig {
+    pub fn is_scraper(&self, req: &HttpRequest) -> bool {
+        let token = match &self.token {
+            Some(token) if !token.is_empty() => token,
+            _ => return false,
+        };
+        let presented = req
+            .headers()
+            .get(header::AUTHORIZATION)
+            .and_then(|value| value.to_str().ok())
+            .and_then(|value| value.strip_prefix("Bearer "));
+        match presented {
+            Some(presented) => admin::same_digest(presented, token),
+            None => false,
+        }
+    }
+}
+
+/// Buckets of the lock waits, from a microsecond up, since an uncontended lock
+/// is taken at once
+const WAIT_BUCKETS: &[f64] = &[1e-6, 1e-5, 1e-4, 1e-3, 0.01, 0.1, 1.0, 10.0];
+
+// Registered in the default registry; all servers of a process share them
+lazy_static! {
+    static ref HTTP_REQUESTS: IntCounterVec = register_int_counter_vec!(
+        "rcvs_http_requests_total",
+        "Requests served, by route pattern, method and status",
+        &["route", "method", "status"]
+    )
+    .expect("Failed to register metric");
+    static ref HTTP_DURATION: HistogramVec = register_histogram_vec!(
+        "rcvs_http_request_duration_seconds",
+        "Time spent serving requests, by route pattern and method",
+        &["route", "method"]
+    )
+    .expect("Failed to register metric");
+    pub static ref BALLOTS_CAST: IntCounter = register_int_counter!(
+        "rcvs_ballots_cast_total",
+        "Ballots cast or replaced since the server started"
+    )
+    .expect("Failed to register metric");
+    pub static ref BALLOTS_DELETED: IntCounter = register_int_counter!(
+        "rcvs_ballots_deleted_total",
+        "Ballots deleted since the server started"
+    )
+    .expect("Failed to register metric");
+    pub static ref BALLOTS: IntGauge = register_int_gauge!(
+        "rcvs_ballots",
+        "Electors who have a ballot in the data base"
+    )
+    .expect("Failed to register metric");
+    pub static ref ELECTION_OPEN: IntGauge = register_int_gauge!(
+        "rcvs_election_open",
+        "1 if the election is open, 0 otherwise"
+    )
+    .expect("Failed to register metric");
+    pub static ref RATE_LIMITED: IntCounter = register_int_counter!(
+        "rcvs_rate_limited_total",
+        "Requests refused for exceeding the rate limits"
+    )
+    .expect("Failed to register metric");
+    pub static ref STATE_LOCK_WAIT: HistogramVec = register_histogram_vec!(
+        "rcvs_state_lock_wait_seconds",
+        "Time waited for the lock of the application state, by mode",
+        &["mode"],
+        WAIT_BUCKETS.to_vec()
+    )
+    .expect("Failed to register metric");
+    pub static ref DATABASE_LOCK_WAIT: Histogram = register_histogram!(
+        "rcvs_database_lock_wait_seconds",
+        "Time waited for the lock of the data base connection",
+        WAIT_BUCKETS.to_vec()
+    )
+    .expect("Failed to register metric");
+    pub static ref STRATEGY_DURATION: Histogram = register_histogram!(
+        "rcvs_strategy_duration_seconds",
+        "Time spent computing optimal strategies"
+    )
+    .expect("Failed to register metric");
+}
+
+/// Every metric in the text format of Prometheus, with its content type
+pub fn render() -> Result<(String, Vec<u8>), prometheus::Error> {
+    let encoder = TextEncoder::new();
+    let mut buffer = Vec::new();
+    encoder.encode(&prometheus::gather(), &mut buffer)?;
+    Ok((encoder.format_type().to_string(), buffer))
+}
+
+/// Read-write lock timing how long it waits before each acquisition. If a
+/// thread panicked while writing, the value is recovered before it is handed
+/// out again.
+pub struct TimedRwLock<T> {
+    lock: qlock::RwLock<T>,
+    read_wait: Histogram,
+    write_wait: Histogram,
+}
+
+impl<T: Recover> TimedRwLock<T> {
+    pub fn new(value: T, wait: &HistogramVec) -> Self {
+        Self {
+            lock: qlock::RwLock::new(value),
+            read_wait: wait.with_label_values(&["read"]),
+            write_wait: wait.with_label_values(&["write"]),
+        }
+    }
+
+    /// Fails only if the value could not be recovered, in which case the lock
+    /// stays poisoned and recovery is tried again on the next acquisition
+    fn recover(&self) -> Result<(), String> {
+        let mut guard = self.lock.write().unwrap_or_else(PoisonError::into_inner);
+        // Another thread may have recovered it while this one waited
+        if self.lock.is_poisoned() {
+            tracing::warn!("Recovering value left by a panic");
+            guard.recover()?;
+            self.lock.clear_poison();
+        }
+        Ok(())
+    }
+
+    pub fn read(&self) -> Result<impl Deref<Target = T> + '_, impl fmt::Display + '_> {
+        let _timer = self.read_wait.start_timer();
+        if self.lock.is_poisoned() {
+            self.recover()?;
+        }
+        self.lock.read().map_err(|what| what.to_string())
+    }
+
+    pub fn write(&self) -> Result<impl DerefMut<Target = T> + '_, impl fmt::Display + '_> {
+        let _timer = self.write_wait.start_timer();
+        if self.lock.is_poisoned() {
+            self.recover()?;
+        }
+        self.lock.write().map_err(|what| what.to_string())
+    }
+}
+
+/// Mutex timing how long it waits before each acquisition, and recovering its
+/// value like `TimedRwLock`
+pub struct TimedMutex<T> {
+    mutex: Mutex<T>,
+    wait: Histogram,
+}
+
+impl<T: Recover> TimedMutex<T> {
+    pub fn new(value: T, wait: &Histogram) -> Self {
+        Self {
+            mutex: Mutex::new(value),
+            wait: wait.clone(),
+        }
+    }
+
+    pub fn lock(&self) -> Result<MutexGuard<'_, T>, String> {
+        let _timer = self.wait.start_timer();
+        match self.mutex.lock() {
+            Ok(guard) => Ok(guard),
+            Err(poisoned) => {
+                tracing::warn!("Recovering value left by a panic");
+                let mut guard = poisoned.into_inner();
+                guard.recover()?;
+                self.mutex.clear_poison();
+                Ok(guard)
+            }
+        }
+    }
+}
+
+impl<T: fmt::Debug> fmt::Debug for TimedMutex<T> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        self.mutex.fmt(f)
+    }
+}
+
+/// Middleware counting requests and timing them by route. Routes are named by
+/// their pattern, such as `/api/alternative/{id}`, so that their number stays
+/// bounded.
+pub struct RequestMetrics;
+
+impl<S, B> Transform<S> for RequestMetrics
+where
+    S: Service<Request = ServiceRequest, Response = ServiceResponse<B>, Error = actix_web::Error>,
+    S::Future: 'static,
+    B: 'static,
+{
+    type Request = ServiceRequest;
+    type Response = ServiceResponse<B>;
+    type Error = actix_web::Error;
+    type InitError = ();
+    type Transform = RequestMetricsMiddleware<S>;
+    type Future = Ready<Result<Self::Transform, Self::InitError>>;
+
+    fn new_transform(&self, service: S) -> Self::Future {
+        ready(Ok(RequestMetricsMiddleware { service: service }))
+    }
+}
+
+pub struct RequestMetricsMiddleware<S> {
+    service: S,
+}
+
+impl<S, B> Service for RequestMetricsMiddleware<S>
+where
+    S: Service<Request = ServiceRequest, Response = ServiceResponse<B>, Error = actix_web::Error>,
+    S::Future: 'static,
+    B: 'static,
+{
+    type Request = ServiceRequest;
+    type Response = ServiceResponse<B>;
+    type Error = actix_web::Error;
+    type Future = Pin<Box<dyn Future<Output = Result<Self::Response, Self::Error>>>>;
+
+    fn poll_ready(&mut self, cx: &mut Context<'_>) -> Poll<Result<(), Self::Error>> {
+        self.service.poll_ready(cx)
+    }
+
+    fn call(&mut self, req: ServiceRequest) -> Self::Future {
+        let route = req
+            .match_pattern()
+            .unwrap_or_else(|| "unmatched".to_string());
+        let method = req.method().to_string();
+        let start = Instant::now();
+        let future = self.service.call(req);
+
+        Box::pin(async move {
+            let response = future.await;
+            let status = match &response {
+                Ok(response) => response.status(),
+                Err(what) => what.as_response_error().status_code(),
+            };
+            HTTP_REQUESTS
+                .with_label_values(&[&route, &method, status.as_str()])
+                .inc();
+            HTTP_DURATION
+                .with_label_values(&[&route, &method])
+                .observe(start.elapsed().as_secs_f64());
+            response
+        })
+    }
+}
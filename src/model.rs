@@ -1,26 +1,58 @@
-use std::{collections::HashMap, error::Error, fs, path::Path};
+use std::time::{SystemTime, UNIX_EPOCH};
+use std::{collections::HashMap, error::Error, path::Path};
 
-use rusqlite::{params, Connection};
-use serde::{Deserialize, Serialize};
+use rand::RngCore;
+use rusqlite::{params, Connection, OpenFlags, OptionalExtension};
+use serde::Deserialize;
+use serde_json::json;
+use sha2::{Digest, Sha256};
+use tracing::{info, instrument};
+
+pub use rcvs_web_types::{
+    AlternativeData, AuditEntry, BallotRecord, BallotRow, Delivery, ElectionData, EnrolledElector,
+    IssuedCredential, RankedCount, TurnoutData,
+};
+
+use crate::markdown;
+use crate::recovery::Recover;
+
+/// Creates the tables of a new data base
+const INIT_CODE: &str = include_str!("../model.sql");
+
+/// Version set by `INIT_CODE`, as `PRAGMA user_version`
+pub const SCHEMA_VERSION: i64 = 3;
+
+/// Brings a data base from the version before it to the one after, starting
+/// from the first schema, which had no version
+const MIGRATIONS: [&str; SCHEMA_VERSION as usize] = [
+    include_str!("../migrations/1.sql"),
+    include_str!("../migrations/2.sql"),
+    include_str!("../migrations/3.sql"),
+];
+
+/// Tables of the first schema
+const FIRST_TABLES: [&str; 3] = ["alternative", "elector", "ranking"];
 
 #[derive(Debug)]
 pub struct DatabaseConnection {
     connection: Connection,
+    anonymous: bool,
+    /// Where the connection is reopened after a panic
+    url: String,
 }
 
 impl DatabaseConnection {
     pub fn new(
         url: &str,
-        init: &str,
         alternatives: &[crate::data::AlternativeData],
-    ) -> rusqlite::Result<Self> {
+        anonymous: bool,
+    ) -> Result<Self, Box<dyn Error>> {
         // FIXME: this is a TOCTOU race condition
         let new = !Path::new(url).exists();
         let mut connection = Connection::open(url)?;
         if new {
-            let model_code = fs::read_to_string(init).expect("Failed to open init code");
             connection
-                .execute_batch(&model_code)
+                .execute_batch(INIT_CODE)
                 .expect("Failed to run init code");
 
             let transaction = connection.transaction()?;
@@ -30,55 +62,289 @@ impl DatabaseConnection {
                     params![alternative.id, alternative.description, alternative.icon],
                 )?;
             }
+            transaction.execute(
+                "INSERT INTO setting VALUES ('anonymous', ?1)",
+                params![anonymous.to_string()],
+            )?;
             transaction.commit()?;
+        } else {
+            migrate(&mut connection)?;
         }
+        check_anonymous(&connection, anonymous)?;
+        Ok(Self {
+            connection: connection,
+            anonymous: anonymous,
+            url: url.to_string(),
+        })
+    }
+
+    /// Opens the data base of an election that already ran, for administration
+    pub fn open(url: &str, anonymous: bool) -> Result<Self, Box<dyn Error>> {
+        let mut connection = Connection::open_with_flags(url, OpenFlags::SQLITE_OPEN_READ_WRITE)?;
+        migrate(&mut connection)?;
+        check_anonymous(&connection, anonymous)?;
         Ok(Self {
             connection: connection,
+            anonymous: anonymous,
+            url: url.to_string(),
         })
     }
+
+    pub fn is_anonymous(&self) -> bool {
+        self.anonymous
+    }
 }
 
-#[derive(Serialize, Deserialize)]
-pub struct BallotRow {
-    #[serde(skip_serializing_if = "Option::is_none")]
-    pub elector: Option<usize>,
-    pub alternative: usize,
-    pub min: u64,
-    pub max: u64,
+/// Runs the migrations from the version of the data base on, each in a
+/// transaction of its own, then enables the foreign keys. Data bases of a
+/// later version are refused rather than misread.
+fn migrate(connection: &mut Connection) -> Result<(), Box<dyn Error>> {
+    let version: i64 = connection.query_row("PRAGMA user_version", params![], |row| row.get(0))?;
+    if version > SCHEMA_VERSION {
+        return Err(format!(
+            "The data base has schema version {}, but this version of rcvs-web only knows up to {}",
+            version, SCHEMA_VERSION
+        )
+        .into());
+    }
+    if version == 0 {
+        let mut statement = connection
+            .prepare("SELECT name FROM sqlite_master WHERE type = 'table' ORDER BY name")?;
+        let tables = statement
+            .query_map(params![], |row| row.get::<usize, String>(0))?
+            .collect::<Result<Vec<String>, _>>()?;
+        if tables != FIRST_TABLES {
+            return Err(format!(
+                "The data base has no schema version and its tables ({}) are not those of the first schema",
+                tables.join(", ")
+            )
+            .into());
+        }
+    }
+
+    // Tables are rebuilt to change their columns, which must not cascade
+    connection.execute_batch("PRAGMA foreign_keys = OFF")?;
+    for (index, code) in MIGRATIONS.iter().enumerate().skip(version as usize) {
+        let transaction = connection.transaction()?;
+        transaction.execute_batch(code)?;
+        transaction.execute_batch(&format!("PRAGMA user_version = {}", index + 1))?;
+        transaction.commit()?;
+        info!(version = index + 1, "Migrated the data base");
+    }
+    enable_foreign_keys(connection)
 }
 
-#[derive(Serialize, Debug, Clone)]
-pub struct AlternativeData {
-    pub id: i64,
-    pub name: String,
-    pub description: String,
-    pub icon: String,
+/// SQLite only enforces foreign keys on the connections which enable them, so
+/// every connection does once it checked that the rows written without them
+/// still refer to existing rows
+fn enable_foreign_keys(connection: &Connection) -> Result<(), Box<dyn Error>> {
+    let mut statement = connection.prepare("PRAGMA foreign_key_check")?;
+    let tables = statement
+        .query_map(params![], |row| row.get::<usize, String>(0))?
+        .collect::<Result<Vec<String>, _>>()?;
+    if !tables.is_empty() {
+        return Err(format!(
+            "The data base has {} rows referring to missing rows, in {}",
+            tables.len(),
+            tables.join(", ")
+        )
+        .into());
+    }
+    connection.execute_batch("PRAGMA foreign_keys = ON")?;
+    Ok(())
 }
 
-#[derive(Serialize)]
-pub struct ElectionData {
-    pub title: Option<String>,
-    pub alternatives: Vec<AlternativeData>,
-    pub ballot: Vec<BallotRow>,
+/// Ballots are stored in different tables depending on the mode, so a data
+/// base is only ever used in the mode it was created with. Data bases from
+/// before the mode was recorded take that of the configuration.
+fn check_anonymous(connection: &Connection, anonymous: bool) -> Result<(), Box<dyn Error>> {
+    let stored: Option<String> = connection
+        .query_row(
+            "SELECT setValue FROM setting WHERE setKey = 'anonymous'",
+            params![],
+            |row| row.get(0),
+        )
+        .optional()?;
+    match stored {
+        Some(stored) if stored != anonymous.to_string() => Err(format!(
+            "The data base was created with \"anonymous\": {}, but the configuration has \"anonymous\": {}",
+            stored, anonymous
+        )
+        .into()),
+        Some(_) => Ok(()),
+        None => {
+            connection.execute(
+                "INSERT INTO setting VALUES ('anonymous', ?1)",
+                params![anonymous.to_string()],
+            )?;
+            Ok(())
+        }
+    }
+}
+
+/// A panic may have left a transaction open or a statement half stepped, so a
+/// new connection is made rather than trusting the old one
+impl Recover for DatabaseConnection {
+    fn recover(&mut self) -> Result<(), String> {
+        let connection = Connection::open_with_flags(&self.url, OpenFlags::SQLITE_OPEN_READ_WRITE)
+            .map_err(|what| format!("Failed to reopen the data base: {}", what))?;
+        enable_foreign_keys(&connection)
+            .map_err(|what| format!("Failed to reopen the data base: {}", what))?;
+        self.connection = connection;
+        Ok(())
+    }
+}
+
+/// Largest weight of an elector, so that the tallies cannot overflow
+pub const MAX_WEIGHT: f64 = 1_000_000.0;
+
+/// Weights are counted in thousandths, so that decimal weights add up exactly
+const WEIGHT_SCALE: f64 = 1000.0;
+
+/// Checks that a weight is a number between 0 and `MAX_WEIGHT`, and rounds it
+/// to the precision it is counted with
+pub fn check_weight(weight: f64) -> Result<f64, String> {
+    // Also false for NaN
+    if !(0.0..=MAX_WEIGHT).contains(&weight) {
+        return Err(format!(
+            "Weight {} is not a number between 0 and {}",
+            weight, MAX_WEIGHT
+        ));
+    }
+    Ok((weight * WEIGHT_SCALE).round() / WEIGHT_SCALE)
+}
+
+fn scaled_weight(weight: f64) -> u64 {
+    (weight * WEIGHT_SCALE).round() as u64
+}
+
+/// Sum of the weights of the ballots, without the rounding errors of adding
+/// the decimals one by one
+pub fn total_weight(ballots: &[WeightedBallot]) -> f64 {
+    ballots.iter().map(|b| scaled_weight(b.weight)).sum::<u64>() as f64 / WEIGHT_SCALE
+}
+
+pub struct WeightedBallot {
+    pub weight: f64,
+    pub rows: Vec<BallotRow>,
 }
 
 pub struct ResultData {
     pub alternatives: Vec<AlternativeData>,
-    pub ballots: HashMap<usize, rcvs::Ballot<usize>>,
+    pub ballots: Vec<WeightedBallot>,
+}
+
+impl ResultData {
+    /// Builds the duel graph as if only the given alternatives were running.
+    ///
+    /// Each ballot adds its weight once to the tally of every duel it decides.
+    /// Then one ballot ranking the winner above the loser is made for every
+    /// duel won by a strict majority of the weights, from which rcvs draws the
+    /// same arrows as it would from the weighted ballots.
+    pub fn duel_graph(&self, alternatives: &[usize]) -> rcvs::DuelGraph<usize> {
+        let mut tally = HashMap::<(usize, usize), u64>::new();
+        for weighted in &self.ballots {
+            let weight = scaled_weight(weighted.weight);
+            if weight == 0 {
+                continue;
+            }
+            let rows: Vec<&BallotRow> = weighted
+                .rows
+                .iter()
+                .filter(|row| alternatives.contains(&row.alternative))
+                .collect();
+            for winner in &rows {
+                for loser in &rows {
+                    if winner.min > loser.max {
+                        *tally
+                            .entry((winner.alternative, loser.alternative))
+                            .or_insert(0) += weight;
+                    }
+                }
+            }
+        }
+
+        let mut duels = Vec::new();
+        for (&(winner, loser), &count) in &tally {
+            if count > tally.get(&(loser, winner)).cloned().unwrap_or(0) {
+                let mut ballot = rcvs::Ballot::new();
+                ballot.insert(winner, 1, 1);
+                ballot.insert(loser, 0, 0);
+                duels.push(ballot);
+            }
+        }
+        rcvs::build_graph(alternatives.iter().cloned(), duels.into_iter())
+    }
+}
+
+/// How an elector is recognized: by IP address, or by the credential issued to
+/// them from the voter roll.
+#[derive(Clone, Debug)]
+pub enum Identity {
+    Ip(String),
+    Credential(String),
+}
+
+impl Identity {
+    fn column(&self) -> &'static str {
+        match self {
+            Self::Ip(_) => "elecIp",
+            Self::Credential(_) => "elecCredential",
+        }
+    }
+
+    fn key(&self) -> &str {
+        match self {
+            Self::Ip(key) | Self::Credential(key) => key,
+        }
+    }
+}
+
+#[derive(Deserialize)]
+pub struct RollEntry {
+    pub name: String,
+    pub email: String,
+    #[serde(default)]
+    pub weight: Option<f64>,
+    #[serde(default)]
+    pub group: Option<String>,
+}
+
+/// Outcome of casting a ballot in anonymous mode.
+pub enum SealedCast {
+    /// First ballot of this elector; the secret must be handed to the voter.
+    Created(String),
+    Replaced,
+    MissingSecret,
+    UnknownSecret,
+}
+
+fn make_secret() -> String {
+    let mut bytes = [0u8; 32];
+    rand::thread_rng().fill_bytes(&mut bytes);
+    hex::encode(bytes)
+}
+
+/// Derives the ballot ID from the voter-held secret. The secret itself is never
+/// stored, so the ballot cannot be linked back to the elector without it.
+fn seal(secret: &str) -> String {
+    hex::encode(Sha256::digest(secret.as_bytes()))
 }
 
 // May be used in the future
 #[allow(dead_code)]
+#[instrument(level = "debug", skip_all)]
 pub fn get_ballot(
     connection: &DatabaseConnection,
-    ip: &str,
+    identity: &Identity,
 ) -> Result<Vec<BallotRow>, Box<dyn Error>> {
     let connection = &connection.connection;
 
-    let mut statement = connection.prepare(
-        "SELECT altId, rankMin, rankMax FROM ranking JOIN elector USING(elecId) WHERE elecIp = ?1",
-    )?;
-    let mut rows = statement.query(params![ip])?;
+    let mut statement = connection.prepare(&format!(
+        "SELECT altId, rankMin, rankMax FROM ranking JOIN elector USING(elecId) WHERE {} = ?1",
+        identity.column()
+    ))?;
+    let mut rows = statement.query(params![identity.key()])?;
 
     let mut ballot = Vec::new();
     while let Some(row) = rows.next()? {
@@ -92,46 +358,118 @@ pub fn get_ballot(
     Ok(ballot)
 }
 
-pub fn delete_ballot(connection: &DatabaseConnection, ip: &str) -> Result<bool, Box<dyn Error>> {
-    let connection = &connection.connection;
+/// Deletes the ballot of an elector. The elector row is kept so that its
+/// weight survives.
+#[instrument(level = "debug", skip_all)]
+pub fn delete_ballot(
+    connection: &mut DatabaseConnection,
+    identity: &Identity,
+) -> Result<bool, Box<dyn Error>> {
+    let connection = &mut connection.connection;
 
-    let deleted = connection.execute("DELETE FROM elector WHERE elecIp = ?1", params![ip])?;
+    let transaction = connection.transaction()?;
+    let elector = get_elector(identity, &transaction)?;
+    let deleted = transaction.execute(
+        &format!(
+            "UPDATE elector SET elecVoted = 0 WHERE {} = ?1 AND elecVoted",
+            identity.column()
+        ),
+        params![identity.key()],
+    )?;
+    transaction.execute(
+        &format!(
+            "DELETE FROM ranking WHERE elecId IN (SELECT elecId FROM elector WHERE {} = ?1)",
+            identity.column()
+        ),
+        params![identity.key()],
+    )?;
+    if deleted != 0 {
+        append_log(
+            &transaction,
+            "ballot_deleted",
+            &json!({ "elector": elector }),
+        )?;
+    }
+    transaction.commit()?;
 
     Ok(deleted != 0)
 }
 
-fn get_elector(ip: &str, connection: &Connection) -> Result<Option<i64>, Box<dyn Error>> {
-    let mut statement = connection.prepare("SELECT elecId FROM elector WHERE elecIp = ?1")?;
-    let mut rows = statement.query(params![ip])?;
+struct ElectorStatus {
+    id: i64,
+    weight: f64,
+    voted: bool,
+}
+
+fn get_elector_status(
+    identity: &Identity,
+    connection: &Connection,
+) -> Result<Option<ElectorStatus>, Box<dyn Error>> {
+    let mut statement = connection.prepare(&format!(
+        "SELECT elecId, elecWeight, elecVoted FROM elector WHERE {} = ?1",
+        identity.column()
+    ))?;
+    let mut rows = statement.query(params![identity.key()])?;
 
     match rows.next()? {
-        Some(row) => Ok(Some(row.get::<usize, i64>(0)?)),
+        Some(row) => Ok(Some(ElectorStatus {
+            id: row.get(0)?,
+            weight: row.get::<usize, f64>(1)?,
+            voted: row.get(2)?,
+        })),
         None => Ok(None),
     }
 }
 
-fn get_put_elector(ip: &str, connection: &Connection) -> Result<i64, Box<dyn Error>> {
-    if let Some(id) = get_elector(ip, connection)? {
+fn get_elector(
+    identity: &Identity,
+    connection: &Connection,
+) -> Result<Option<i64>, Box<dyn Error>> {
+    Ok(get_elector_status(identity, connection)?.map(|status| status.id))
+}
+
+/// Electors known by their IP address are registered on the fly, whereas
+/// credentials only exist once issued from the voter roll.
+fn get_put_elector(identity: &Identity, connection: &Connection) -> Result<i64, Box<dyn Error>> {
+    if let Some(id) = get_elector(identity, connection)? {
         Ok(id)
-    } else {
-        connection.execute("INSERT INTO elector VALUES(null, ?1)", params![ip])?;
+    } else if let Identity::Ip(ip) = identity {
+        connection.execute("INSERT INTO elector(elecIp) VALUES(?1)", params![ip])?;
 
-        match get_elector(ip, connection)? {
+        match get_elector(identity, connection)? {
             Some(id) => Ok(id),
-            None => panic!("Inserting did not work"),
+            None => Err("Failed to register the elector".into()),
         }
+    } else {
+        Err("Elector is not on the voter roll".into())
     }
 }
 
+#[instrument(level = "debug", skip_all)]
+pub fn is_enrolled(
+    connection: &DatabaseConnection,
+    identity: &Identity,
+) -> Result<bool, Box<dyn Error>> {
+    match identity {
+        Identity::Ip(_) => Ok(true),
+        Identity::Credential(_) => Ok(get_elector(identity, &connection.connection)?.is_some()),
+    }
+}
+
+#[instrument(level = "debug", skip_all)]
 pub fn set_ballot(
     connection: &mut DatabaseConnection,
-    ip: &str,
+    identity: &Identity,
     ballot: &[BallotRow],
 ) -> Result<(), Box<dyn Error>> {
     let connection = &mut connection.connection;
 
     let transaction = connection.transaction()?;
-    let elector = get_put_elector(ip, &transaction)?;
+    let elector = get_put_elector(identity, &transaction)?;
+    transaction.execute(
+        "UPDATE elector SET elecVoted = 1 WHERE elecId = ?1",
+        params![elector],
+    )?;
     transaction.execute("DELETE FROM ranking WHERE elecId = ?1", params![elector])?;
     for row in ballot {
         transaction.execute(
@@ -144,6 +482,11 @@ pub fn set_ballot(
             ],
         )?;
     }
+    append_log(
+        &transaction,
+        "ballot_cast",
+        &json!({ "elector": elector, "ballot": ballot }),
+    )?;
     transaction.commit()?;
 
     Ok(())
@@ -154,10 +497,12 @@ fn build_alternative_vector(
 ) -> rusqlite::Result<Vec<AlternativeData>> {
     let mut statement = transaction.prepare("SELECT * FROM alternative")?;
     let iterator = statement.query_map(params![], |row| {
+        let description = row.get::<usize, Option<String>>(2)?.unwrap_or_default();
         Ok(AlternativeData {
             id: row.get(0)?,
             name: row.get(1)?,
-            description: row.get::<usize, Option<String>>(2)?.unwrap_or_default(),
+            description_html: markdown::to_html(&description),
+            description: description,
             icon: row.get::<usize, Option<String>>(3)?.unwrap_or_default(),
         })
     })?;
@@ -169,19 +514,67 @@ fn build_alternative_vector(
     Ok(vector)
 }
 
+/// Replaces the icon of an alternative. Returns the former icon, or `None` if
+/// the alternative does not exist.
+#[instrument(level = "debug", skip_all)]
+pub fn set_alternative_icon(
+    connection: &mut DatabaseConnection,
+    alternative: i64,
+    icon: &str,
+) -> Result<Option<String>, Box<dyn Error>> {
+    let transaction = connection.connection.transaction()?;
+    let former = match transaction.query_row(
+        "SELECT altIcon FROM alternative WHERE altId = ?1",
+        params![alternative],
+        |row| row.get::<usize, Option<String>>(0),
+    ) {
+        Ok(icon) => icon.unwrap_or_default(),
+        Err(rusqlite::Error::QueryReturnedNoRows) => return Ok(None),
+        Err(what) => return Err(what.into()),
+    };
+    transaction.execute(
+        "UPDATE alternative SET altIcon = ?1 WHERE altId = ?2",
+        params![icon, alternative],
+    )?;
+    append_log(
+        &transaction,
+        "icon_changed",
+        &json!({ "alternative": alternative, "icon": icon }),
+    )?;
+    transaction.commit()?;
+    Ok(Some(former))
+}
+
+#[instrument(level = "debug", skip_all)]
 pub fn get_data(
     connection: &mut DatabaseConnection,
-    ip: &str,
+    identity: &Identity,
+    secret: Option<&str>,
 ) -> Result<ElectionData, Box<dyn Error>> {
+    let anonymous = connection.anonymous;
     let connection = &mut connection.connection;
     let transaction = connection.transaction()?;
 
     let alternatives = build_alternative_vector(&transaction)?;
+    let weight = get_elector_status(identity, &transaction)?.map_or(1.0, |status| status.weight);
 
-    let mut statement = transaction.prepare(
-        "SELECT altId, rankMin, rankMax FROM ranking JOIN elector USING(elecId) WHERE elecIp = ?1",
-    )?;
-    let ballot_iter = statement.query_map(params![ip], |row| {
+    // Without the secret, an anonymous elector gets an empty ballot
+    let (query, key) = if anonymous {
+        (
+            "SELECT altId, rankMin, rankMax FROM sealedRanking WHERE ballotId = ?1".to_string(),
+            secret.map(seal).unwrap_or_default(),
+        )
+    } else {
+        (
+            format!(
+                "SELECT altId, rankMin, rankMax FROM ranking JOIN elector USING(elecId) WHERE {} = ?1",
+                identity.column()
+            ),
+            identity.key().to_string(),
+        )
+    };
+    let mut statement = transaction.prepare(&query)?;
+    let ballot_iter = statement.query_map(params![key], |row| {
         Ok(BallotRow {
             elector: None,
             alternative: row.get::<usize, i64>(0)? as usize,
@@ -199,40 +592,824 @@ pub fn get_data(
         title: None,
         alternatives: alternatives,
         ballot: ballot,
+        weight: weight,
     })
 }
 
+#[instrument(level = "debug", skip_all)]
 pub fn collect_votes(connection: &mut DatabaseConnection) -> Result<ResultData, Box<dyn Error>> {
+    if connection.anonymous {
+        return collect_sealed_votes(connection);
+    }
     let connection = &mut connection.connection;
     let transaction = connection.transaction()?;
 
     let alternatives = build_alternative_vector(&transaction)?;
 
-    let mut statement = transaction.prepare("SELECT * FROM ranking")?;
-    let ballot_iter = statement.query_map(params![], |row| {
-        Ok(BallotRow {
-            elector: Some(row.get::<usize, i64>(0)? as usize),
-            alternative: row.get::<usize, i64>(1)? as usize,
-            min: row.get::<usize, i64>(2)? as u64,
-            max: row.get::<usize, i64>(3)? as u64,
+    let mut statement = transaction.prepare(
+        "SELECT elecId, elecWeight, altId, rankMin, rankMax
+        FROM ranking JOIN elector USING(elecId) WHERE elecVoted",
+    )?;
+    let mut rows = statement.query(params![])?;
+
+    let mut ballots = HashMap::<usize, WeightedBallot>::new();
+    while let Some(row) = rows.next()? {
+        let elector = row.get::<usize, i64>(0)? as usize;
+        let weight = row.get::<usize, f64>(1)?;
+        ballots
+            .entry(elector)
+            .or_insert_with(|| WeightedBallot {
+                weight: weight,
+                rows: Vec::new(),
+            })
+            .rows
+            .push(BallotRow {
+                elector: Some(elector),
+                alternative: row.get::<usize, i64>(2)? as usize,
+                min: row.get::<usize, i64>(3)? as u64,
+                max: row.get::<usize, i64>(4)? as u64,
+            });
+    }
+
+    Ok(ResultData {
+        alternatives: alternatives,
+        ballots: ballots.into_iter().map(|(_, ballot)| ballot).collect(),
+    })
+}
+
+#[instrument(level = "debug", skip_all)]
+pub fn cast_sealed_ballot(
+    connection: &mut DatabaseConnection,
+    identity: &Identity,
+    secret: Option<&str>,
+    ballot: &[BallotRow],
+) -> Result<SealedCast, Box<dyn Error>> {
+    let connection = &mut connection.connection;
+
+    let transaction = connection.transaction()?;
+    let status = get_elector_status(identity, &transaction)?;
+    let (ballot_id, outcome) = match (status, secret) {
+        (Some(ElectorStatus { voted: true, .. }), None) => return Ok(SealedCast::MissingSecret),
+        (Some(ElectorStatus { voted: true, .. }), Some(secret)) => {
+            let ballot_id = seal(secret);
+            if !sealed_ballot_exists(&ballot_id, &transaction)? {
+                return Ok(SealedCast::UnknownSecret);
+            }
+            transaction.execute(
+                "DELETE FROM sealedRanking WHERE ballotId = ?1",
+                params![ballot_id],
+            )?;
+            (ballot_id, SealedCast::Replaced)
+        }
+        (status, _) => {
+            let elector = get_put_elector(identity, &transaction)?;
+            transaction.execute(
+                "UPDATE elector SET elecVoted = 1 WHERE elecId = ?1",
+                params![elector],
+            )?;
+            // The weight is copied at casting time since the ballot cannot be
+            // traced back to its elector afterwards
+            let weight = status.map_or(1.0, |status| status.weight);
+            let secret = make_secret();
+            let ballot_id = seal(&secret);
+            transaction.execute(
+                "INSERT INTO sealedBallot VALUES(?1, ?2)",
+                params![ballot_id, weight],
+            )?;
+            (ballot_id, SealedCast::Created(secret))
+        }
+    };
+    for row in ballot {
+        transaction.execute(
+            "INSERT INTO sealedRanking VALUES(?1, ?2, ?3, ?4)",
+            params![
+                ballot_id,
+                row.alternative as i64,
+                row.min as i64,
+                row.max as i64
+            ],
+        )?;
+    }
+    // Neither the elector nor the ballot, which would link them
+    append_log(&transaction, "ballot_cast", &json!({}))?;
+    transaction.commit()?;
+
+    Ok(outcome)
+}
+
+/// Empties an anonymous ballot. The elector stays marked as having voted so
+/// that the secret can still be used to cast a new ballot later.
+#[instrument(level = "debug", skip_all)]
+pub fn clear_sealed_ballot(
+    connection: &mut DatabaseConnection,
+    secret: &str,
+) -> Result<bool, Box<dyn Error>> {
+    let transaction = connection.connection.transaction()?;
+    let ballot_id = seal(secret);
+
+    if !sealed_ballot_exists(&ballot_id, &transaction)? {
+        return Ok(false);
+    }
+    transaction.execute(
+        "DELETE FROM sealedRanking WHERE ballotId = ?1",
+        params![ballot_id],
+    )?;
+    append_log(&transaction, "ballot_deleted", &json!({}))?;
+    transaction.commit()?;
+
+    Ok(true)
+}
+
+fn sealed_ballot_exists(ballot_id: &str, connection: &Connection) -> Result<bool, Box<dyn Error>> {
+    let mut statement = connection.prepare("SELECT 1 FROM sealedBallot WHERE ballotId = ?1")?;
+    let mut rows = statement.query(params![ballot_id])?;
+
+    Ok(rows.next()?.is_some())
+}
+
+fn collect_sealed_votes(connection: &mut DatabaseConnection) -> Result<ResultData, Box<dyn Error>> {
+    let connection = &mut connection.connection;
+    let transaction = connection.transaction()?;
+
+    let alternatives = build_alternative_vector(&transaction)?;
+
+    let mut statement = transaction.prepare(
+        "SELECT ballotId, ballotWeight, altId, rankMin, rankMax
+        FROM sealedRanking JOIN sealedBallot USING(ballotId)",
+    )?;
+    let mut rows = statement.query(params![])?;
+
+    let mut ballots = HashMap::<String, WeightedBallot>::new();
+    while let Some(row) = rows.next()? {
+        let ballot_id: String = row.get(0)?;
+        let weight = row.get::<usize, f64>(1)?;
+        ballots
+            .entry(ballot_id)
+            .or_insert_with(|| WeightedBallot {
+                weight: weight,
+                rows: Vec::new(),
+            })
+            .rows
+            .push(BallotRow {
+                elector: None,
+                alternative: row.get::<usize, i64>(2)? as usize,
+                min: row.get::<usize, i64>(3)? as u64,
+                max: row.get::<usize, i64>(4)? as u64,
+            });
+    }
+
+    Ok(ResultData {
+        alternatives: alternatives,
+        ballots: ballots.into_iter().map(|(_, ballot)| ballot).collect(),
+    })
+}
+
+/// Sets the weight of an elector, registering it if needed. Returns `false` if
+/// the weight can no longer change because the elector already cast an
+/// anonymous ballot.
+#[instrument(level = "debug", skip_all)]
+pub fn set_elector_weight(
+    connection: &mut DatabaseConnection,
+    identity: &Identity,
+    weight: f64,
+) -> Result<bool, Box<dyn Error>> {
+    let weight = check_weight(weight)?;
+    let anonymous = connection.anonymous;
+    let connection = &mut connection.connection;
+
+    let transaction = connection.transaction()?;
+    if anonymous {
+        if let Some(ElectorStatus { voted: true, .. }) = get_elector_status(identity, &transaction)?
+        {
+            return Ok(false);
+        }
+    }
+    let elector = get_put_elector(identity, &transaction)?;
+    transaction.execute(
+        "UPDATE elector SET elecWeight = ?2 WHERE elecId = ?1",
+        params![elector, weight],
+    )?;
+    append_log(
+        &transaction,
+        "weight_set",
+        &json!({ "elector": elector, "weight": weight }),
+    )?;
+    transaction.commit()?;
+
+    Ok(true)
+}
+
+/// Enrolls the entries of the voter roll, keyed by email address. New voters
+/// are issued a credential; voters already enrolled keep theirs.
+#[instrument(level = "debug", skip_all)]
+pub fn import_roll(
+    connection: &mut DatabaseConnection,
+    roll: &[RollEntry],
+) -> Result<Vec<IssuedCredential>, Box<dyn Error>> {
+    let connection = &mut connection.connection;
+
+    let transaction = connection.transaction()?;
+    let mut issued = Vec::new();
+    for entry in roll {
+        transaction.execute(
+            "INSERT INTO elector(elecCredential, elecName, elecEmail, elecGroup, elecWeight)
+            VALUES(?1, ?2, ?3, ?4, ?5)
+            ON CONFLICT(elecEmail) DO UPDATE SET
+                elecName = excluded.elecName,
+                elecGroup = excluded.elecGroup,
+                elecWeight = excluded.elecWeight",
+            params![
+                make_secret(),
+                entry.name,
+                entry.email,
+                entry.group,
+                check_weight(entry.weight.unwrap_or(1.0))?
+            ],
+        )?;
+        let credential: String = transaction.query_row(
+            "SELECT elecCredential FROM elector WHERE elecEmail = ?1",
+            params![entry.email],
+            |row| row.get(0),
+        )?;
+        issued.push(IssuedCredential {
+            name: entry.name.to_string(),
+            email: entry.email.to_string(),
+            credential: credential,
+        });
+    }
+    // The credentials must not be written anywhere else
+    append_log(
+        &transaction,
+        "roll_imported",
+        &json!({ "voters": roll.len() }),
+    )?;
+    transaction.commit()?;
+
+    Ok(issued)
+}
+
+/// Lists the voter roll with whether each voter has voted, but not how.
+#[instrument(level = "debug", skip_all)]
+pub fn get_roll(connection: &DatabaseConnection) -> Result<Vec<EnrolledElector>, Box<dyn Error>> {
+    let connection = &connection.connection;
+
+    let mut statement = connection.prepare(
+        "SELECT elecName, elecEmail, elecGroup, elecWeight, elecVoted
+        FROM elector WHERE elecCredential IS NOT NULL ORDER BY elecName",
+    )?;
+    let iterator = statement.query_map(params![], |row| {
+        Ok(EnrolledElector {
+            name: row.get::<usize, Option<String>>(0)?.unwrap_or_default(),
+            email: row.get::<usize, Option<String>>(1)?.unwrap_or_default(),
+            group: row.get(2)?,
+            weight: row.get::<usize, f64>(3)?,
+            voted: row.get(4)?,
         })
     })?;
 
-    let mut ballots = HashMap::<usize, rcvs::Ballot<usize>>::new();
-    for row_res in ballot_iter {
-        let row = row_res?;
-        let elector = row.elector.unwrap();
-        if let Some(ballot) = ballots.get_mut(&elector) {
-            ballot.insert(row.alternative, row.min, row.max);
+    let mut roll = Vec::new();
+    for elector in iterator {
+        roll.push(elector?);
+    }
+    Ok(roll)
+}
+
+/// Lists the credentials of enrolled voters, optionally restricted to those who
+/// have not voted yet.
+#[instrument(level = "debug", skip_all)]
+pub fn get_roll_credentials(
+    connection: &DatabaseConnection,
+    non_voters_only: bool,
+) -> Result<Vec<IssuedCredential>, Box<dyn Error>> {
+    let connection = &connection.connection;
+
+    let mut statement = connection.prepare(
+        "SELECT elecName, elecEmail, elecCredential FROM elector
+        WHERE elecCredential IS NOT NULL AND elecEmail IS NOT NULL AND NOT (?1 AND elecVoted)",
+    )?;
+    let iterator = statement.query_map(params![non_voters_only], |row| {
+        Ok(IssuedCredential {
+            name: row.get::<usize, Option<String>>(0)?.unwrap_or_default(),
+            email: row.get(1)?,
+            credential: row.get(2)?,
+        })
+    })?;
+
+    let mut credentials = Vec::new();
+    for credential in iterator {
+        credentials.push(credential?);
+    }
+    Ok(credentials)
+}
+
+#[instrument(level = "debug", skip_all)]
+pub fn get_setting(
+    connection: &DatabaseConnection,
+    key: &str,
+) -> Result<Option<String>, Box<dyn Error>> {
+    let value = connection
+        .connection
+        .query_row(
+            "SELECT setValue FROM setting WHERE setKey = ?1",
+            params![key],
+            |row| row.get(0),
+        )
+        .optional()?;
+    Ok(value)
+}
+
+#[instrument(level = "debug", skip_all)]
+pub fn set_setting(
+    connection: &DatabaseConnection,
+    key: &str,
+    value: &str,
+) -> Result<(), Box<dyn Error>> {
+    connection.connection.execute(
+        "INSERT INTO setting VALUES(?1, ?2)
+        ON CONFLICT(setKey) DO UPDATE SET setValue = excluded.setValue",
+        params![key, value],
+    )?;
+    Ok(())
+}
+
+/// Version of the schema the data base was created with, which also checks
+/// that it can be read
+#[instrument(level = "debug", skip_all)]
+pub fn schema_version(connection: &DatabaseConnection) -> Result<i64, Box<dyn Error>> {
+    let version = connection
+        .connection
+        .query_row("PRAGMA user_version", params![], |row| row.get(0))?;
+    Ok(version)
+}
+
+/// Number of ballots holding a ranking, as counted by `collect_votes`. Emptied
+/// anonymous ballots leave their elector marked as having voted, so the
+/// electors cannot be counted instead.
+#[instrument(level = "debug", skip_all)]
+pub fn count_ballots(connection: &DatabaseConnection) -> Result<u64, Box<dyn Error>> {
+    let query = if connection.anonymous {
+        "SELECT COUNT(DISTINCT ballotId) FROM sealedRanking"
+    } else {
+        "SELECT COUNT(DISTINCT elecId) FROM ranking JOIN elector USING(elecId) WHERE elecVoted"
+    };
+    let count: i64 = connection
+        .connection
+        .query_row(query, params![], |row| row.get(0))?;
+    Ok(count as u64)
+}
+
+#[instrument(level = "debug", skip_all)]
+pub fn log_delivery(
+    connection: &DatabaseConnection,
+    delivery: &Delivery,
+) -> Result<(), Box<dyn Error>> {
+    connection.connection.execute(
+        "INSERT INTO webhookDelivery VALUES(null, ?1, ?2, ?3, ?4, ?5, ?6)",
+        params![
+            delivery.url,
+            delivery.event,
+            delivery.attempt,
+            delivery.status,
+            delivery.error,
+            delivery.time as i64
+        ],
+    )?;
+    Ok(())
+}
+
+#[instrument(level = "debug", skip_all)]
+pub fn get_deliveries(connection: &DatabaseConnection) -> Result<Vec<Delivery>, Box<dyn Error>> {
+    let connection = &connection.connection;
+
+    let mut statement = connection.prepare(
+        "SELECT delivUrl, delivEvent, delivAttempt, delivStatus, delivError, delivTime
+        FROM webhookDelivery ORDER BY delivId",
+    )?;
+    let iterator = statement.query_map(params![], |row| {
+        Ok(Delivery {
+            url: row.get(0)?,
+            event: row.get(1)?,
+            attempt: row.get(2)?,
+            status: row.get(3)?,
+            error: row.get(4)?,
+            time: row.get::<usize, i64>(5)? as u64,
+        })
+    })?;
+
+    let mut deliveries = Vec::new();
+    for delivery in iterator {
+        deliveries.push(delivery?);
+    }
+    Ok(deliveries)
+}
+
+#[instrument(level = "debug", skip_all)]
+pub fn get_alternatives(
+    connection: &mut DatabaseConnection,
+) -> Result<Vec<AlternativeData>, Box<dyn Error>> {
+    let transaction = connection.connection.transaction()?;
+    Ok(build_alternative_vector(&transaction)?)
+}
+
+/// Adds an alternative to a running election. Returns its identifier.
+#[instrument(level = "debug", skip_all)]
+pub fn add_alternative(
+    connection: &mut DatabaseConnection,
+    name: &str,
+    description: &str,
+) -> Result<i64, Box<dyn Error>> {
+    let transaction = connection.connection.transaction()?;
+    transaction.execute(
+        "INSERT INTO alternative VALUES(null, ?1, ?2, '')",
+        params![name, description],
+    )?;
+    let id = transaction.last_insert_rowid();
+    append_log(
+        &transaction,
+        "alternative_added",
+        &json!({ "alternative": id, "name": name, "description": description }),
+    )?;
+    transaction.commit()?;
+    Ok(id)
+}
+
+/// Renames an alternative and replaces its description. Returns `false` if
+/// there is no such alternative.
+#[instrument(level = "debug", skip_all)]
+pub fn update_alternative(
+    connection: &mut DatabaseConnection,
+    id: i64,
+    name: &str,
+    description: &str,
+) -> Result<bool, Box<dyn Error>> {
+    let transaction = connection.connection.transaction()?;
+    let updated = transaction.execute(
+        "UPDATE alternative SET altName = ?2, altDescription = ?3 WHERE altId = ?1",
+        params![id, name, description],
+    )?;
+    if updated == 0 {
+        return Ok(false);
+    }
+    append_log(
+        &transaction,
+        "alternative_edited",
+        &json!({ "alternative": id, "name": name, "description": description }),
+    )?;
+    transaction.commit()?;
+    Ok(true)
+}
+
+/// Removes an alternative along with the ranks it was given. Returns its
+/// icon, or `None` if there is no such alternative.
+#[instrument(level = "debug", skip_all)]
+pub fn remove_alternative(
+    connection: &mut DatabaseConnection,
+    id: i64,
+) -> Result<Option<String>, Box<dyn Error>> {
+    let transaction = connection.connection.transaction()?;
+    let (name, icon) = {
+        let mut statement =
+            transaction.prepare("SELECT altName, altIcon FROM alternative WHERE altId = ?1")?;
+        let mut rows = statement.query(params![id])?;
+        match rows.next()? {
+            Some(row) => (
+                row.get::<usize, String>(0)?,
+                row.get::<usize, Option<String>>(1)?.unwrap_or_default(),
+            ),
+            None => return Ok(None),
+        }
+    };
+    // Its ranks go with it, since every connection enforces the foreign keys
+    transaction.execute("DELETE FROM alternative WHERE altId = ?1", params![id])?;
+    append_log(
+        &transaction,
+        "alternative_removed",
+        &json!({ "alternative": id, "name": name }),
+    )?;
+    transaction.commit()?;
+    Ok(Some(icon))
+}
+
+/// Adds ballots exported from another data base. Their electors cannot be
+/// known, so each one is recorded under an elector of its own.
+#[instrument(level = "debug", skip_all)]
+pub fn import_ballots(
+    connection: &mut DatabaseConnection,
+    ballots: &[WeightedBallot],
+) -> Result<(), Box<dyn Error>> {
+    let anonymous = connection.anonymous;
+    let transaction = connection.connection.transaction()?;
+    for ballot in ballots {
+        let key = make_secret();
+        if anonymous {
+            let ballot_id = seal(&key);
+            transaction.execute(
+                "INSERT INTO sealedBallot VALUES(?1, ?2)",
+                params![ballot_id, check_weight(ballot.weight)?],
+            )?;
+            for row in &ballot.rows {
+                transaction.execute(
+                    "INSERT INTO sealedRanking VALUES(?1, ?2, ?3, ?4)",
+                    params![
+                        ballot_id,
+                        row.alternative as i64,
+                        row.min as i64,
+                        row.max as i64
+                    ],
+                )?;
+            }
         } else {
-            let mut ballot = rcvs::Ballot::new();
-            ballot.insert(row.alternative, row.min, row.max);
-            ballots.insert(elector, ballot);
+            // Not an address, so that no elector can ever be recognized as it
+            transaction.execute(
+                "INSERT INTO elector(elecIp, elecWeight, elecVoted) VALUES(?1, ?2, 1)",
+                params![format!("imported:{}", key), check_weight(ballot.weight)?],
+            )?;
+            let elector = transaction.last_insert_rowid();
+            for row in &ballot.rows {
+                transaction.execute(
+                    "INSERT INTO ranking VALUES(?1, ?2, ?3, ?4)",
+                    params![
+                        elector,
+                        row.alternative as i64,
+                        row.min as i64,
+                        row.max as i64
+                    ],
+                )?;
+            }
         }
     }
+    append_log(
+        &transaction,
+        "ballots_imported",
+        &json!({ "ballots": ballots.len() }),
+    )?;
+    transaction.commit()?;
+    Ok(())
+}
 
-    Ok(ResultData {
-        alternatives: alternatives,
-        ballots: ballots,
+/// Copies the data base to a new file, even while the server is running.
+#[instrument(level = "debug", skip_all)]
+pub fn backup(connection: &DatabaseConnection, path: &str) -> Result<(), Box<dyn Error>> {
+    connection
+        .connection
+        .execute("VACUUM INTO ?1", params![path])?;
+    Ok(())
+}
+
+/// Counts the ballots, in total and per alternative, and the enrolled voters
+/// who voted
+#[instrument(level = "debug", skip_all)]
+pub fn get_turnout(
+    connection: &mut DatabaseConnection,
+    open: bool,
+) -> Result<TurnoutData, Box<dyn Error>> {
+    let data = collect_votes(connection)?;
+    let roll = get_roll(connection)?;
+    let ranked = data
+        .alternatives
+        .iter()
+        .map(|alternative| RankedCount {
+            alternative: alternative.id as usize,
+            ballots: data
+                .ballots
+                .iter()
+                .filter(|b| {
+                    b.rows
+                        .iter()
+                        .any(|r| r.alternative == alternative.id as usize)
+                })
+                .count(),
+        })
+        .collect();
+    Ok(TurnoutData {
+        open: open,
+        ballots: data.ballots.len(),
+        weight: total_weight(&data.ballots),
+        enrolled: roll.len(),
+        voted: roll.iter().filter(|e| e.voted).count(),
+        ranked: ranked,
     })
 }
+
+/// Lists the rows of every ballot, numbered from 1, with the names of the
+/// alternatives
+#[instrument(level = "debug", skip_all)]
+pub fn export_ballots(
+    connection: &mut DatabaseConnection,
+) -> Result<Vec<BallotRecord>, Box<dyn Error>> {
+    let data = collect_votes(connection)?;
+    let names: HashMap<usize, &str> = data
+        .alternatives
+        .iter()
+        .map(|a| (a.id as usize, a.name.as_str()))
+        .collect();
+    let mut records = Vec::new();
+    for (number, ballot) in data.ballots.iter().enumerate() {
+        for row in &ballot.rows {
+            records.push(BallotRecord {
+                ballot: number + 1,
+                weight: ballot.weight,
+                alternative: names.get(&row.alternative).unwrap_or(&"").to_string(),
+                min: row.min,
+                max: row.max,
+            });
+        }
+    }
+    Ok(records)
+}
+
+fn now() -> i64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|d| d.as_secs() as i64)
+        .unwrap_or(0)
+}
+
+/// Digest of an entry of the audit log, which covers the digest of the
+/// previous entry
+fn chain_digest(previous: &str, id: i64, time: i64, event: &str, data: &str) -> String {
+    let mut hasher = Sha256::new();
+    for part in &[previous, &id.to_string(), &time.to_string(), event, data] {
+        hasher.update(part.as_bytes());
+        hasher.update(b"\n");
+    }
+    hex::encode(hasher.finalize())
+}
+
+/// Must be called within the transaction making the change, so that the
+/// entry is chained to the right one
+fn append_log(
+    connection: &Connection,
+    event: &str,
+    data: &serde_json::Value,
+) -> rusqlite::Result<()> {
+    let (id, previous) = match connection.query_row(
+        "SELECT logId, logHash FROM auditLog ORDER BY logId DESC LIMIT 1",
+        params![],
+        |row| Ok((row.get::<usize, i64>(0)?, row.get::<usize, String>(1)?)),
+    ) {
+        Ok((id, hash)) => (id + 1, hash),
+        Err(rusqlite::Error::QueryReturnedNoRows) => (1, String::new()),
+        Err(what) => return Err(what),
+    };
+    let time = now();
+    let data = data.to_string();
+    connection.execute(
+        "INSERT INTO auditLog VALUES(?1, ?2, ?3, ?4, ?5)",
+        params![
+            id,
+            time,
+            event,
+            data,
+            chain_digest(&previous, id, time, event, &data)
+        ],
+    )?;
+    Ok(())
+}
+
+/// Records an event which changes nothing else in the data base, such as a
+/// login or a change of the configuration
+#[instrument(level = "debug", skip_all)]
+pub fn log_event(
+    connection: &mut DatabaseConnection,
+    event: &str,
+    data: &serde_json::Value,
+) -> Result<(), Box<dyn Error>> {
+    let transaction = connection.connection.transaction()?;
+    append_log(&transaction, event, data)?;
+    transaction.commit()?;
+    Ok(())
+}
+
+#[instrument(level = "debug", skip_all)]
+pub fn get_audit_log(connection: &DatabaseConnection) -> Result<Vec<AuditEntry>, Box<dyn Error>> {
+    let mut statement = connection.connection.prepare(
+        "SELECT logId, logTime, logEvent, logData, logHash FROM auditLog ORDER BY logId",
+    )?;
+    let mut rows = statement.query(params![])?;
+
+    let mut entries = Vec::new();
+    while let Some(row) = rows.next()? {
+        entries.push(AuditEntry {
+            id: row.get(0)?,
+            time: row.get::<usize, i64>(1)? as u64,
+            event: row.get(2)?,
+            data: serde_json::from_str(&row.get::<usize, String>(3)?)?,
+            hash: row.get(4)?,
+        });
+    }
+    Ok(entries)
+}
+
+/// Recomputes the hash chain of a whole audit log. Returns the identifier of
+/// the first entry which does not match, if any.
+///
+/// Entries cut off the end of the log leave the chain intact; the hash of the
+/// last entry must be kept elsewhere to notice that.
+pub fn verify_audit_log(entries: &[AuditEntry]) -> Option<i64> {
+    let mut previous = String::new();
+    for entry in entries {
+        let digest = chain_digest(
+            &previous,
+            entry.id,
+            entry.time as i64,
+            &entry.event,
+            &entry.data.to_string(),
+        );
+        if digest != entry.hash {
+            return Some(entry.id);
+        }
+        previous = digest;
+    }
+    None
+}
+
+/// Records the result of closing the election, with the seed of the
+/// generator the winners were drawn with
+#[instrument(level = "debug", skip_all)]
+pub fn record_draw(
+    connection: &mut DatabaseConnection,
+    seed: &str,
+    result: &rcvs_web_types::ResultData,
+) -> Result<(), Box<dyn Error>> {
+    let transaction = connection.connection.transaction()?;
+    transaction.execute(
+        "INSERT INTO draw VALUES(null, ?1, ?2, ?3)",
+        params![now(), seed, serde_json::to_string(result)?],
+    )?;
+    let draw = transaction.last_insert_rowid();
+    append_log(
+        &transaction,
+        "closed",
+        &json!({ "draw": draw, "seed": seed, "winners": result.winners }),
+    )?;
+    transaction.commit()?;
+    Ok(())
+}
+
+#[instrument(level = "debug", skip_all)]
+pub fn count_draws(connection: &DatabaseConnection) -> Result<usize, Box<dyn Error>> {
+    let count: i64 =
+        connection
+            .connection
+            .query_row("SELECT COUNT(*) FROM draw", params![], |row| row.get(0))?;
+    Ok(count as usize)
+}
+
+/// Records that the election was reopened, along with every draw made so far
+#[instrument(level = "debug", skip_all)]
+pub fn record_reopening(connection: &mut DatabaseConnection) -> Result<(), Box<dyn Error>> {
+    let transaction = connection.connection.transaction()?;
+    let mut draws = Vec::new();
+    {
+        let mut statement = transaction
+            .prepare("SELECT drawId, drawTime, drawSeed, drawResult FROM draw ORDER BY drawId")?;
+        let mut rows = statement.query(params![])?;
+        while let Some(row) = rows.next()? {
+            let result: rcvs_web_types::ResultData =
+                serde_json::from_str(&row.get::<usize, String>(3)?)?;
+            draws.push(json!({
+                "draw": row.get::<usize, i64>(0)?,
+                "time": row.get::<usize, i64>(1)?,
+                "seed": row.get::<usize, String>(2)?,
+                "winners": result.winners,
+            }));
+        }
+    }
+    append_log(&transaction, "reopened", &json!({ "draws": draws }))?;
+    transaction.commit()?;
+    Ok(())
+}
+
+/// Result of the last draw, unless the election was reopened since
+#[instrument(level = "debug", skip_all)]
+pub fn get_final_result(
+    connection: &DatabaseConnection,
+) -> Result<Option<rcvs_web_types::ResultData>, Box<dyn Error>> {
+    Ok(get_final_draw(connection)?.map(|(_, result)| result))
+}
+
+/// Seed and result of the last draw, unless the election was reopened since
+#[instrument(level = "debug", skip_all)]
+pub fn get_final_draw(
+    connection: &DatabaseConnection,
+) -> Result<Option<(String, rcvs_web_types::ResultData)>, Box<dyn Error>> {
+    let connection = &connection.connection;
+    let mut statement = connection.prepare(
+        "SELECT logEvent, logData FROM auditLog
+        WHERE logEvent IN ('closed', 'reopened') ORDER BY logId DESC LIMIT 1",
+    )?;
+    let mut rows = statement.query(params![])?;
+    let data: serde_json::Value = match rows.next()? {
+        Some(row) if row.get::<usize, String>(0)? == "closed" => {
+            serde_json::from_str(&row.get::<usize, String>(1)?)?
+        }
+        _ => return Ok(None),
+    };
+    let (seed, result): (String, String) = connection.query_row(
+        "SELECT drawSeed, drawResult FROM draw WHERE drawId = ?1",
+        params![data["draw"].as_i64()],
+        |row| Ok((row.get(0)?, row.get(1)?)),
+    )?;
+    Ok(Some((seed, serde_json::from_str(&result)?)))
+}
@@ -0,0 +1,439 @@
+use schemars::gen::{SchemaGenerator, SchemaSettings};
+use schemars::JsonSchema;
+use serde_json::{json, Value};
+
+use crate::{mailer, model};
+use crate::{AlternativeEdit, IconData, ResultData, SecretData, TitleData, WeightData};
+use crate::{BALLOT_SECRET_HEADER, CREDENTIAL_HEADER};
+
+/// Image types accepted for icons, see `assets::store_icon`
+const ICON_TYPES: &[&str] = &["image/png", "image/jpeg", "image/gif", "image/webp"];
+
+fn schema<T: JsonSchema>(generator: &mut SchemaGenerator) -> Value {
+    serde_json::to_value(generator.subschema_for::<T>()).unwrap_or_default()
+}
+
+/// Response without a body
+fn empty(description: &str) -> Value {
+    json!({ "description": description })
+}
+
+/// Response with a plain text body, as errors are returned
+fn text(description: &str) -> Value {
+    json!({
+        "description": description,
+        "content": { "text/plain": { "schema": { "type": "string" } } },
+    })
+}
+
+fn json(description: &str, schema: Value) -> Value {
+    json!({
+        "description": description,
+        "content": { "application/json": { "schema": schema } },
+    })
+}
+
+fn reference(parameter: &str) -> Value {
+    json!({ "$ref": format!("#/components/parameters/{}", parameter) })
+}
+
+/// Parameters of the requests made by electors
+fn elector_parameters() -> Value {
+    json!([
+        reference("credential"),
+        reference("secret"),
+        reference("lang"),
+        reference("accept-language"),
+    ])
+}
+
+/// Builds the OpenAPI 3 document of the `/api` scope. The schemas are derived
+/// from the types the handlers serialize and deserialize.
+pub fn document() -> Value {
+    let mut generator = SchemaSettings::openapi3().into_generator();
+    let election = schema::<model::ElectionData>(&mut generator);
+    let ballot = schema::<Vec<model::BallotRow>>(&mut generator);
+    let secret = schema::<SecretData>(&mut generator);
+    let result = schema::<ResultData>(&mut generator);
+    let weight = schema::<WeightData>(&mut generator);
+    let icon = schema::<IconData>(&mut generator);
+    let roll = schema::<Vec<model::EnrolledElector>>(&mut generator);
+    let credentials = schema::<Vec<model::IssuedCredential>>(&mut generator);
+    let report = schema::<mailer::MailReport>(&mut generator);
+    let deliveries = schema::<Vec<model::Delivery>>(&mut generator);
+    let title = schema::<TitleData>(&mut generator);
+    let edit = schema::<AlternativeEdit>(&mut generator);
+    let alternative = schema::<model::AlternativeData>(&mut generator);
+    let turnout = schema::<model::TurnoutData>(&mut generator);
+    let audit = schema::<Vec<model::AuditEntry>>(&mut generator);
+    let schemas = serde_json::to_value(generator.take_definitions()).unwrap_or_default();
+
+    let icon_content: serde_json::Map<String, Value> = ICON_TYPES
+        .iter()
+        .map(|t| {
+            (
+                t.to_string(),
+                json!({ "schema": { "type": "string", "format": "binary" } }),
+            )
+        })
+        .collect();
+
+    json!({
+        "openapi": "3.0.3",
+        "info": {
+            "title": env!("CARGO_PKG_NAME"),
+            "description": env!("CARGO_PKG_DESCRIPTION"),
+            "version": env!("CARGO_PKG_VERSION"),
+        },
+        "servers": [{ "url": "/api" }],
+        "tags": [
+            { "name": "elector", "description": "Casting ballots and reading the result" },
+            {
+                "name": "admin",
+                "description": "Running the election; only allowed from loopback or with the session cookie of an administrator logged in at `/admin/login`. Requests other than `GET` are refused if their `Origin` header names another site, and, when they rely on the session cookie, if they lack the `X-CSRF-Token` header holding the token of the session, which `/admin` embeds.",
+            },
+        ],
+        "paths": {
+            "/": {
+                "get": {
+                    "tags": ["elector"],
+                    "summary": "Alternatives of the election and ballot of the elector",
+                    "operationId": "getInfo",
+                    "parameters": elector_parameters(),
+                    "responses": {
+                        "200": json("Election data", election.clone()),
+                        "401": text("Missing voter credential"),
+                    },
+                },
+            },
+            "/ballot": {
+                "get": {
+                    "tags": ["elector"],
+                    "summary": "Same as `GET /`",
+                    "operationId": "getBallot",
+                    "parameters": elector_parameters(),
+                    "responses": {
+                        "200": json("Election data", election),
+                        "401": text("Missing voter credential"),
+                    },
+                },
+                "post": {
+                    "tags": ["elector"],
+                    "summary": "Casts or replaces the ballot of the elector",
+                    "operationId": "postBallot",
+                    "parameters": elector_parameters(),
+                    "requestBody": {
+                        "required": true,
+                        "content": { "application/json": { "schema": ballot } },
+                    },
+                    "responses": {
+                        "201": json("First ballot of an anonymous election", secret),
+                        "204": empty("Ballot cast"),
+                        "400": text("Malformed ballot"),
+                        "401": text("Missing voter credential"),
+                        "403": text("Election closed, elector not enrolled, or ballot secret missing or unknown"),
+                        "413": empty("Ballot larger than the configured limit"),
+                        "429": text("Too many requests; `Retry-After` gives the seconds to wait"),
+                    },
+                },
+                "delete": {
+                    "tags": ["elector"],
+                    "summary": "Withdraws the ballot of the elector",
+                    "operationId": "deleteBallot",
+                    "parameters": elector_parameters(),
+                    "responses": {
+                        "204": empty("Ballot deleted"),
+                        "401": text("Missing voter credential"),
+                        "403": text("Election closed or ballot secret missing"),
+                        "404": text("No ballot to delete"),
+                        "429": text("Too many requests; `Retry-After` gives the seconds to wait"),
+                    },
+                },
+            },
+            "/result": {
+                "get": {
+                    "tags": ["elector"],
+                    "summary": "Final result, or the result if the election closed now",
+                    "operationId": "getResult",
+                    "responses": { "200": json("Result", result) },
+                },
+            },
+            "/elector/{ip}": {
+                "put": {
+                    "tags": ["admin"],
+                    "summary": "Sets the weight of the elector voting from an address",
+                    "operationId": "setWeight",
+                    "parameters": [{
+                        "name": "ip",
+                        "in": "path",
+                        "required": true,
+                        "schema": { "type": "string" },
+                    }],
+                    "requestBody": {
+                        "required": true,
+                        "content": { "application/json": { "schema": weight } },
+                    },
+                    "responses": {
+                        "204": empty("Weight set"),
+                        "400": text("Bad elector address, or weight out of bounds"),
+                        "403": text("Not an administrator"),
+                        "409": text("The election has a voter roll, or the elector already cast an anonymous ballot with its former weight"),
+                    },
+                },
+            },
+            "/title": {
+                "put": {
+                    "tags": ["admin"],
+                    "summary": "Renames the election, in its configuration as well",
+                    "operationId": "setTitle",
+                    "requestBody": {
+                        "required": true,
+                        "content": { "application/json": { "schema": title } },
+                    },
+                    "responses": {
+                        "204": empty("Title set"),
+                        "400": text("Empty title"),
+                        "403": text("Not an administrator"),
+                        "409": text("Election closed"),
+                    },
+                },
+            },
+            "/alternatives": {
+                "post": {
+                    "tags": ["admin"],
+                    "summary": "Adds an alternative",
+                    "operationId": "addAlternative",
+                    "requestBody": {
+                        "required": true,
+                        "content": { "application/json": { "schema": edit.clone() } },
+                    },
+                    "responses": {
+                        "201": json("Added alternative", alternative),
+                        "400": text("Empty name"),
+                        "403": text("Not an administrator"),
+                        "409": text("Election closed, or name already taken"),
+                    },
+                },
+            },
+            "/alternative/{id}": {
+                "put": {
+                    "tags": ["admin"],
+                    "summary": "Renames an alternative and replaces its description",
+                    "operationId": "editAlternative",
+                    "parameters": [reference("alternative")],
+                    "requestBody": {
+                        "required": true,
+                        "content": { "application/json": { "schema": edit } },
+                    },
+                    "responses": {
+                        "204": empty("Alternative edited"),
+                        "400": text("Empty name"),
+                        "403": text("Not an administrator"),
+                        "404": text("No such alternative"),
+                        "409": text("Election closed, or name already taken"),
+                    },
+                },
+                "delete": {
+                    "tags": ["admin"],
+                    "summary": "Removes an alternative along with the ranks it was given",
+                    "operationId": "removeAlternative",
+                    "parameters": [reference("alternative")],
+                    "responses": {
+                        "204": empty("Alternative removed"),
+                        "403": text("Not an administrator"),
+                        "404": text("No such alternative"),
+                        "409": text("Election closed"),
+                    },
+                },
+            },
+            "/alternative/{id}/icon": {
+                "put": {
+                    "tags": ["admin"],
+                    "summary": "Uploads the icon of an alternative",
+                    "operationId": "uploadIcon",
+                    "parameters": [reference("alternative")],
+                    "requestBody": { "required": true, "content": icon_content },
+                    "responses": {
+                        "200": json("Path of the stored icon", icon),
+                        "400": text("Invalid image"),
+                        "403": text("Not an administrator"),
+                        "404": text("No such alternative"),
+                        "413": empty("Image too large"),
+                        "415": text("Unsupported image type"),
+                    },
+                },
+            },
+            "/roll": {
+                "get": {
+                    "tags": ["admin"],
+                    "summary": "Voter roll, with who already voted",
+                    "operationId": "getRoll",
+                    "responses": {
+                        "200": json("Voter roll", roll),
+                        "403": text("Not an administrator"),
+                    },
+                },
+                "post": {
+                    "tags": ["admin"],
+                    "summary": "Imports a voter roll and issues the credentials",
+                    "operationId": "importRoll",
+                    "requestBody": {
+                        "required": true,
+                        "content": { "text/csv": { "schema": { "type": "string" } } },
+                    },
+                    "responses": {
+                        "200": json("Issued credentials", credentials),
+                        "400": text("Bad voter roll"),
+                        "403": text("Not an administrator"),
+                    },
+                },
+            },
+            "/mail/invitations": {
+                "post": {
+                    "tags": ["admin"],
+                    "summary": "Mails their voting link to the enrolled voters",
+                    "operationId": "sendInvitations",
+                    "responses": {
+                        "200": json("Mailing report", report.clone()),
+                        "403": text("Not an administrator, or election closed"),
+                        "404": text("Mail is not configured"),
+                    },
+                },
+            },
+            "/mail/reminders": {
+                "post": {
+                    "tags": ["admin"],
+                    "summary": "Reminds the enrolled voters who did not vote yet",
+                    "operationId": "sendReminders",
+                    "responses": {
+                        "200": json("Mailing report", report),
+                        "403": text("Not an administrator, or election closed"),
+                        "404": text("Mail is not configured"),
+                    },
+                },
+            },
+            "/webhooks/deliveries": {
+                "get": {
+                    "tags": ["admin"],
+                    "summary": "Log of the webhook deliveries",
+                    "operationId": "getDeliveries",
+                    "responses": {
+                        "200": json("Deliveries", deliveries),
+                        "403": text("Not an administrator"),
+                    },
+                },
+            },
+            "/turnout": {
+                "get": {
+                    "tags": ["admin"],
+                    "summary": "Number of ballots, in total and per alternative, and of enrolled voters who voted",
+                    "operationId": "getTurnout",
+                    "responses": {
+                        "200": json("Turnout", turnout),
+                        "403": text("Not an administrator"),
+                    },
+                },
+            },
+            "/export/ballots": {
+                "get": {
+                    "tags": ["admin"],
+                    "summary": "Rows of every ballot, as imported by `rcvs-admin ballots import`",
+                    "operationId": "exportBallots",
+                    "responses": {
+                        "200": {
+                            "description": "Columns `ballot`, `weight`, `alternative`, `min` and `max`; the rows of a ballot share its number",
+                            "content": { "text/csv": { "schema": { "type": "string" } } },
+                        },
+                        "403": text("Not an administrator"),
+                    },
+                },
+            },
+            "/audit": {
+                "get": {
+                    "tags": ["admin"],
+                    "summary": "Audit log of the administrative and voter actions, oldest first",
+                    "description": "The `hash` of each entry is the hexadecimal SHA-256 of the `hash` of the previous entry (empty for the first one), `id`, `time`, `event` and `data` as compact JSON, each followed by a line feed.",
+                    "operationId": "getAudit",
+                    "responses": {
+                        "200": json("Audit log", audit),
+                        "403": text("Not an administrator"),
+                    },
+                },
+            },
+            "/export/audit": {
+                "get": {
+                    "tags": ["admin"],
+                    "summary": "Audit log as JSON Lines, one entry per line",
+                    "operationId": "exportAudit",
+                    "responses": {
+                        "200": {
+                            "description": "Entries as returned by `/audit`",
+                            "content": { "application/x-ndjson": { "schema": { "type": "string" } } },
+                        },
+                        "403": text("Not an administrator"),
+                    },
+                },
+            },
+            "/close": {
+                "post": {
+                    "tags": ["admin"],
+                    "summary": "Closes the election and draws the winners",
+                    "operationId": "close",
+                    "responses": {
+                        "204": empty("Election closed"),
+                        "403": text("Not an administrator"),
+                        "409": text("Election already closed; the winners are not drawn again"),
+                    },
+                },
+            },
+            "/open": {
+                "post": {
+                    "tags": ["admin"],
+                    "summary": "Opens the election",
+                    "operationId": "open",
+                    "responses": {
+                        "204": empty("Election opened"),
+                        "403": text("Not an administrator"),
+                        "409": text("Election already open, or closed for good as `allow_reopen` is not set"),
+                    },
+                },
+            },
+        },
+        "components": {
+            "schemas": schemas,
+            "parameters": {
+                "credential": {
+                    "name": CREDENTIAL_HEADER,
+                    "in": "header",
+                    "description": "Credential of the voter, if the election has a voter roll",
+                    "schema": { "type": "string" },
+                },
+                "secret": {
+                    "name": BALLOT_SECRET_HEADER,
+                    "in": "header",
+                    "description": "Ballot secret, if the election is anonymous",
+                    "schema": { "type": "string" },
+                },
+                "lang": {
+                    "name": "lang",
+                    "in": "query",
+                    "description": "Language of the error messages",
+                    "schema": { "type": "string" },
+                },
+                "alternative": {
+                    "name": "id",
+                    "in": "path",
+                    "required": true,
+                    "description": "Identifier of the alternative",
+                    "schema": { "type": "integer", "format": "int64" },
+                },
+                "accept-language": {
+                    "name": "Accept-Language",
+                    "in": "header",
+                    "description": "Language of the error messages, if `lang` is not given",
+                    "schema": { "type": "string" },
+                },
+            },
+        },
+    })
+}
@@ -0,0 +1,242 @@
+use std::collections::HashMap;
+use std::future::{ready, Ready};
+use std::sync::{Arc, Mutex};
+use std::task::{Context, Poll};
+use std::time::Instant;
+
+use actix_web::dev::{Service, ServiceRequest, ServiceResponse, Transform};
+use actix_web::http::{header, Method};
+use actix_web::{web, HttpResponse};
+use futures_util::future::Either;
+use serde::Deserialize;
+
+use crate::i18n;
+
+/// Beyond this many buckets, those which have refilled are forgotten
+const MAX_IDLE_BUCKETS: usize = 4096;
+
+/// Token bucket: `burst` requests at once, then `per_minute` on average
+#[derive(Deserialize, Clone, Copy, Debug)]
+pub struct BucketConfig {
+    pub burst: u32,
+    pub per_minute: u32,
+}
+
+#[derive(Deserialize, Clone, Debug)]
+pub struct RateLimitConfig {
+    /// Requests from one IP address, `null` for no limit
+    #[serde(default = "default_per_ip")]
+    pub per_ip: Option<BucketConfig>,
+    /// Requests carrying one voter credential, `null` for no limit
+    #[serde(default = "default_per_credential")]
+    pub per_credential: Option<BucketConfig>,
+    /// Largest ballot accepted through the REST interface, in bytes
+    #[serde(default = "default_max_ballot_size")]
+    pub max_ballot_size: usize,
+}
+
+// Generous enough for electors sharing an address behind a NAT
+fn default_per_ip() -> Option<BucketConfig> {
+    Some(BucketConfig {
+        burst: 30,
+        per_minute: 60,
+    })
+}
+
+fn default_per_credential() -> Option<BucketConfig> {
+    Some(BucketConfig {
+        burst: 10,
+        per_minute: 10,
+    })
+}
+
+fn default_max_ballot_size() -> usize {
+    64 * 1024
+}
+
+impl Default for RateLimitConfig {
+    fn default() -> Self {
+        Self {
+            per_ip: default_per_ip(),
+            per_credential: default_per_credential(),
+            max_ballot_size: default_max_ballot_size(),
+        }
+    }
+}
+
+#[derive(Debug)]
+struct Bucket {
+    tokens: f64,
+    updated: Instant,
+}
+
+impl Bucket {
+    fn refill(&mut self, config: BucketConfig, now: Instant) {
+        let rate = f64::from(config.per_minute) / 60.0;
+        let elapsed = now.duration_since(self.updated).as_secs_f64();
+        self.tokens = (self.tokens + elapsed * rate).min(f64::from(config.burst));
+        self.updated = now;
+    }
+
+    /// Seconds to wait before a token is available, if none is
+    fn wait(&self, config: BucketConfig) -> Option<u64> {
+        if self.tokens >= 1.0 {
+            None
+        } else if config.per_minute == 0 {
+            // Never refilled; a day is as good an answer as any
+            Some(24 * 60 * 60)
+        } else {
+            let rate = f64::from(config.per_minute) / 60.0;
+            Some(((1.0 - self.tokens) / rate).ceil() as u64)
+        }
+    }
+}
+
+/// Buckets of every client, keyed by IP address or credential
+#[derive(Debug)]
+pub struct RateLimiter {
+    config: RateLimitConfig,
+    buckets: Mutex<HashMap<String, Bucket>>,
+}
+
+impl RateLimiter {
+    pub fn new(config: &RateLimitConfig) -> Self {
+        Self {
+            config: config.clone(),
+            buckets: Mutex::new(HashMap::new()),
+        }
+    }
+
+    pub fn max_ballot_size(&self) -> usize {
+        self.config.max_ballot_size
+    }
+
+    /// Takes a token from every bucket the request falls in, or none if one
+    /// of them is empty, in which case the longest wait is returned
+    fn take(&self, keys: &[(String, BucketConfig)]) -> Result<(), u64> {
+        let now = Instant::now();
+        let mut buckets = match self.buckets.lock() {
+            Ok(buckets) => buckets,
+            // Buckets are only ever left consistent
+            Err(poisoned) => poisoned.into_inner(),
+        };
+        if buckets.len() > MAX_IDLE_BUCKETS {
+            let config = &self.config;
+            buckets.retain(|key, bucket| {
+                let limit = if key.starts_with("ip:") {
+                    config.per_ip
+                } else {
+                    config.per_credential
+                };
+                match limit {
+                    Some(limit) => {
+                        bucket.refill(limit, now);
+                        bucket.tokens < f64::from(limit.burst)
+                    }
+                    None => false,
+                }
+            });
+        }
+
+        let mut wait = None;
+        for (key, config) in keys {
+            let bucket = buckets.entry(key.clone()).or_insert(Bucket {
+                tokens: f64::from(config.burst),
+                updated: now,
+            });
+            bucket.refill(*config, now);
+            wait = wait.max(bucket.wait(*config));
+        }
+        if let Some(wait) = wait {
+            return Err(wait);
+        }
+        for (key, _) in keys {
+            if let Some(bucket) = buckets.get_mut(key) {
+                bucket.tokens -= 1.0;
+            }
+        }
+        Ok(())
+    }
+
+    fn keys(&self, req: &ServiceRequest) -> Vec<(String, BucketConfig)> {
+        let mut keys = Vec::new();
+        if let (Some(limit), Some(address)) = (self.config.per_ip, req.peer_addr()) {
+            keys.push((format!("ip:{}", address.ip()), limit));
+        }
+        if let (Some(limit), Some(credential)) = (
+            self.config.per_credential,
+            crate::get_credential(req.headers(), req.query_string()),
+        ) {
+            keys.push((format!("credential:{}", credential), limit));
+        }
+        keys
+    }
+}
+
+/// Middleware refusing requests beyond the limits with 429 Too Many Requests
+/// and a `Retry-After` header. Reading is not limited, only the requests that
+/// write, so it wraps the resources of ballots and logins.
+#[derive(Clone)]
+pub struct RateLimit(pub Arc<RateLimiter>);
+
+impl<S> Transform<S> for RateLimit
+where
+    S: Service<Request = ServiceRequest, Response = ServiceResponse, Error = actix_web::Error>,
+{
+    type Request = ServiceRequest;
+    type Response = ServiceResponse;
+    type Error = actix_web::Error;
+    type InitError = ();
+    type Transform = RateLimitMiddleware<S>;
+    type Future = Ready<Result<Self::Transform, Self::InitError>>;
+
+    fn new_transform(&self, service: S) -> Self::Future {
+        ready(Ok(RateLimitMiddleware {
+            service: service,
+            limiter: self.0.clone(),
+        }))
+    }
+}
+
+pub struct RateLimitMiddleware<S> {
+    service: S,
+    limiter: Arc<RateLimiter>,
+}
+
+impl<S> Service for RateLimitMiddleware<S>
+where
+    S: Service<Request = ServiceRequest, Response = ServiceResponse, Error = actix_web::Error>,
+{
+    type Request = ServiceRequest;
+    type Response = ServiceResponse;
+    type Error = actix_web::Error;
+    type Future = Either<S::Future, Ready<Result<Self::Response, Self::Error>>>;
+
+    fn poll_ready(&mut self, cx: &mut Context<'_>) -> Poll<Result<(), Self::Error>> {
+        self.service.poll_ready(cx)
+    }
+
+    fn call(&mut self, req: ServiceRequest) -> Self::Future {
+        if req.method() == Method::GET || req.method() == Method::HEAD {
+            return Either::Left(self.service.call(req));
+        }
+        let wait = match self.limiter.take(&self.limiter.keys(&req)) {
+            Ok(()) => return Either::Left(self.service.call(req)),
+            Err(wait) => wait,
+        };
+
+        tracing::warn!(retry_after = wait, "Rate limited");
+        crate::metrics::RATE_LIMITED.inc();
+        let (req, _) = req.into_parts();
+        let body = match req.app_data::<web::Data<Arc<i18n::Catalogs>>>() {
+            Some(catalogs) => catalogs
+                .negotiate(&req)
+                .tr_with("error-rate-limited", &[("seconds", wait.to_string())]),
+            None => "Too many requests".to_string(),
+        };
+        let response = HttpResponse::TooManyRequests()
+            .header(header::RETRY_AFTER, wait.to_string())
+            .body(body);
+        Either::Right(ready(Ok(ServiceResponse::new(req, response))))
+    }
+}
@@ -0,0 +1,90 @@
+use std::any::Any;
+use std::future::{ready, Future, Ready};
+use std::panic::{self, AssertUnwindSafe};
+use std::pin::Pin;
+use std::task::{Context, Poll};
+
+use actix_web::dev::{Service, ServiceRequest, ServiceResponse, Transform};
+use futures_util::FutureExt;
+
+/// Values that can be brought back to a consistent state after a thread
+/// panicked while holding their lock
+pub trait Recover {
+    fn recover(&mut self) -> Result<(), String>;
+}
+
+fn panic_message(payload: &(dyn Any + Send)) -> &str {
+    if let Some(message) = payload.downcast_ref::<&str>() {
+        message
+    } else if let Some(message) = payload.downcast_ref::<String>() {
+        message
+    } else {
+        "unknown panic"
+    }
+}
+
+/// Middleware turning a panicking handler into a 500 response, instead of
+/// dropping the connection. The locks it held are recovered by the next
+/// request taking them.
+pub struct CatchPanic;
+
+impl<S, B> Transform<S> for CatchPanic
+where
+    S: Service<Request = ServiceRequest, Response = ServiceResponse<B>, Error = actix_web::Error>,
+    S::Future: 'static,
+    B: 'static,
+{
+    type Request = ServiceRequest;
+    type Response = ServiceResponse<B>;
+    type Error = actix_web::Error;
+    type InitError = ();
+    type Transform = CatchPanicMiddleware<S>;
+    type Future = Ready<Result<Self::Transform, Self::InitError>>;
+
+    fn new_transform(&self, service: S) -> Self::Future {
+        ready(Ok(CatchPanicMiddleware { service: service }))
+    }
+}
+
+pub struct CatchPanicMiddleware<S> {
+    service: S,
+}
+
+impl<S, B> Service for CatchPanicMiddleware<S>
+where
+    S: Service<Request = ServiceRequest, Response = ServiceResponse<B>, Error = actix_web::Error>,
+    S::Future: 'static,
+    B: 'static,
+{
+    type Request = ServiceRequest;
+    type Response = ServiceResponse<B>;
+    type Error = actix_web::Error;
+    type Future = Pin<Box<dyn Future<Output = Result<Self::Response, Self::Error>>>>;
+
+    fn poll_ready(&mut self, cx: &mut Context<'_>) -> Poll<Result<(), Self::Error>> {
+        self.service.poll_ready(cx)
+    }
+
+    fn call(&mut self, req: ServiceRequest) -> Self::Future {
+        let service = &mut self.service;
+        let future = match panic::catch_unwind(AssertUnwindSafe(|| service.call(req))) {
+            Ok(future) => future,
+            Err(payload) => {
+                tracing::error!(panic = panic_message(&*payload), "Handler panicked");
+                return Box::pin(ready(Err(actix_web::error::ErrorInternalServerError(
+                    "Internal error",
+                ))));
+            }
+        };
+
+        Box::pin(async move {
+            match AssertUnwindSafe(future).catch_unwind().await {
+                Ok(response) => response,
+                Err(payload) => {
+                    tracing::error!(panic = panic_message(&*payload), "Handler panicked");
+                    Err(actix_web::error::ErrorInternalServerError("Internal error"))
+                }
+            }
+        })
+    }
+}
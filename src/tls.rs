@@ -0,0 +1,120 @@
+use std::error::Error;
+use std::fs::File;
+use std::io::BufReader;
+
+use actix_web::http::header;
+use actix_web::{middleware, web, HttpRequest, HttpResponse};
+use rustls::internal::pemfile::{certs, pkcs8_private_keys, rsa_private_keys};
+use rustls::{NoClientAuth, ServerConfig};
+use serde::Deserialize;
+
+#[derive(Deserialize, Clone, Debug)]
+pub struct TlsConfig {
+    /// PEM file of the certificate, followed by its chain
+    certificate: String,
+    /// PEM file of the private key, in PKCS #8 or PKCS #1
+    key: String,
+    /// Where HTTPS is served
+    #[serde(default = "default_address")]
+    address: String,
+    /// Where plain HTTP is served, only to redirect to HTTPS
+    #[serde(default)]
+    redirect_from: Option<String>,
+    /// How long browsers keep to HTTPS once they saw it, in seconds
+    #[serde(default = "default_hsts_max_age")]
+    hsts_max_age: u64,
+}
+
+fn default_address() -> String {
+    "0.0.0.0:8443".to_string()
+}
+
+fn default_hsts_max_age() -> u64 {
+    365 * 24 * 60 * 60
+}
+
+impl TlsConfig {
+    pub fn address(&self) -> &str {
+        &self.address
+    }
+
+    pub fn redirect_from(&self) -> Option<&str> {
+        self.redirect_from.as_deref()
+    }
+
+    /// Reads the certificate and key
+    pub fn load(&self) -> Result<ServerConfig, Box<dyn Error>> {
+        let mut reader = BufReader::new(File::open(&self.certificate)?);
+        let chain = certs(&mut reader)
+            .map_err(|()| format!("{} is not a PEM certificate", self.certificate))?;
+        if chain.is_empty() {
+            return Err(format!("No certificate in {}", self.certificate).into());
+        }
+
+        let mut reader = BufReader::new(File::open(&self.key)?);
+        let mut keys = pkcs8_private_keys(&mut reader)
+            .map_err(|()| format!("{} is not a PEM private key", self.key))?;
+        if keys.is_empty() {
+            let mut reader = BufReader::new(File::open(&self.key)?);
+            keys = rsa_private_keys(&mut reader)
+                .map_err(|()| format!("{} is not a PEM private key", self.key))?;
+        }
+        let key = match keys.into_iter().next() {
+            Some(key) => key,
+            None => return Err(format!("No private key in {}", self.key).into()),
+        };
+
+        let mut config = ServerConfig::new(NoClientAuth::new());
+        config.set_single_cert(chain, key)?;
+        Ok(config)
+    }
+
+    pub fn hsts(&self) -> middleware::DefaultHeaders {
+        middleware::DefaultHeaders::new().header(
+            header::STRICT_TRANSPORT_SECURITY,
+            format!("max-age={}", self.hsts_max_age),
+        )
+    }
+
+    fn port(&self) -> Option<u16> {
+        self.address.rsplit(':').next()?.parse().ok()
+    }
+
+    /// Routes every request to the same URL over HTTPS, for the listener of
+    /// `redirect_from`
+    pub fn configure_redirect(&self, config: &mut web::ServiceConfig) {
+        config
+            .data(HttpsPort(self.port()))
+            .route("/{tail:.*}", web::to(redirect_to_https));
+    }
+}
+
+struct HttpsPort(Option<u16>);
+
+/// Name of the host a request was sent to, without its port
+fn host_name(host: &str) -> &str {
+    match host.find(']') {
+        // IPv6 address in brackets
+        Some(end) if host.starts_with('[') => &host[..=end],
+        _ => host.split(':').next().unwrap_or(host),
+    }
+}
+
+/// Permanent redirect which, being a 308, keeps the method and body, so that
+/// a POST is retried over HTTPS
+async fn redirect_to_https(req: HttpRequest, port: web::Data<HttpsPort>) -> HttpResponse {
+    let connection = req.connection_info();
+    let host = host_name(connection.host());
+    let path = req
+        .uri()
+        .path_and_query()
+        .map(|p| p.as_str())
+        .unwrap_or("/");
+    let location = match port.0 {
+        Some(443) | None => format!("https://{}{}", host, path),
+        Some(port) => format!("https://{}:{}{}", host, port, path),
+    };
+    HttpResponse::PermanentRedirect()
+        .header(header::LOCATION, location)
+        .finish()
+}
@@ -0,0 +1,178 @@
+use std::error::Error;
+use std::sync::Arc;
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+use hmac::{Hmac, Mac, NewMac};
+use serde::Deserialize;
+use sha2::Sha256;
+use tracing::{error, warn, Instrument};
+
+use crate::metrics::TimedMutex;
+use crate::model;
+
+/// Header carrying the HMAC-SHA256 of the request body, keyed with the secret
+/// shared with the receiver
+pub const SIGNATURE_HEADER: &str = "X-Rcvs-Signature";
+
+/// Key of the setting listing the milestones already notified, so that a
+/// restart does not notify them again
+const MILESTONES_REACHED: &str = "milestones_reached";
+
+#[derive(Deserialize, Clone, Debug)]
+pub struct WebhookConfig {
+    url: String,
+    secret: String,
+    #[serde(default = "default_attempts")]
+    attempts: u32,
+}
+
+fn default_attempts() -> u32 {
+    5
+}
+
+#[derive(Debug)]
+pub struct Webhooks {
+    hooks: Vec<WebhookConfig>,
+    milestones: Vec<u64>,
+    database: Arc<TimedMutex<model::DatabaseConnection>>,
+}
+
+fn now() -> u64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|d| d.as_secs())
+        .unwrap_or(0)
+}
+
+pub fn sign(secret: &str, body: &[u8]) -> String {
+    let mut mac =
+        Hmac::<Sha256>::new_varkey(secret.as_bytes()).expect("HMAC accepts keys of any size");
+    mac.update(body);
+    format!("sha256={}", hex::encode(mac.finalize().into_bytes()))
+}
+
+impl Webhooks {
+    pub fn new(
+        hooks: &[WebhookConfig],
+        milestones: &[u64],
+        database: Arc<TimedMutex<model::DatabaseConnection>>,
+    ) -> Self {
+        Self {
+            hooks: hooks.to_vec(),
+            milestones: milestones.to_vec(),
+            database: database,
+        }
+    }
+
+    /// Notifies every milestone the number of ballots has reached and that was
+    /// not notified yet. The caller holds the data base, so two ballots cast at
+    /// once cannot both notify a milestone, nor both skip it.
+    pub fn ballot_count(
+        &self,
+        database: &model::DatabaseConnection,
+        count: u64,
+    ) -> Result<(), Box<dyn Error>> {
+        let mut reached: Vec<u64> = match model::get_setting(database, MILESTONES_REACHED)? {
+            Some(value) => serde_json::from_str(&value)?,
+            None => Vec::new(),
+        };
+        let mut milestones: Vec<u64> = self
+            .milestones
+            .iter()
+            .filter(|&&m| m <= count && !reached.contains(&m))
+            .cloned()
+            .collect();
+        if milestones.is_empty() {
+            return Ok(());
+        }
+        milestones.sort_unstable();
+        milestones.dedup();
+        reached.extend(&milestones);
+        model::set_setting(
+            database,
+            MILESTONES_REACHED,
+            &serde_json::to_string(&reached)?,
+        )?;
+        for milestone in milestones {
+            self.notify(
+                "ballot_milestone",
+                serde_json::json!({ "ballots": milestone }),
+            );
+        }
+        Ok(())
+    }
+
+    /// Posts the event to every configured webhook in the background
+    pub fn notify(&self, event: &str, data: serde_json::Value) {
+        let body = serde_json::json!({
+            "event": event,
+            "timestamp": now(),
+            "data": data,
+        })
+        .to_string();
+        for hook in &self.hooks {
+            // Deliveries are logged under the request which caused them
+            actix_rt::spawn(
+                deliver(
+                    hook.clone(),
+                    event.to_string(),
+                    body.clone(),
+                    self.database.clone(),
+                )
+                .in_current_span(),
+            );
+        }
+    }
+}
+
+/// Tries to deliver an event until the receiver answers with a success status,
+/// doubling the delay between attempts. Every attempt is logged.
+async fn deliver(
+    hook: WebhookConfig,
+    event: String,
+    body: String,
+    database: Arc<TimedMutex<model::DatabaseConnection>>,
+) {
+    let signature = sign(&hook.secret, body.as_bytes());
+    let client = awc::Client::default();
+    for attempt in 1..=hook.attempts.max(1) {
+        let response = client
+            .post(&hook.url)
+            .content_type("application/json")
+            .set_header(SIGNATURE_HEADER, signature.as_str())
+            .timeout(Duration::from_secs(10))
+            .send_body(body.clone())
+            .await;
+        let (status, error) = match response {
+            Ok(r) if r.status().is_success() => (Some(r.status().as_u16()), None),
+            Ok(r) => (
+                Some(r.status().as_u16()),
+                Some("Unsuccessful status".to_string()),
+            ),
+            Err(what) => (None, Some(what.to_string())),
+        };
+        let delivery = model::Delivery {
+            url: hook.url.to_string(),
+            event: event.to_string(),
+            attempt: attempt,
+            status: status,
+            error: error.clone(),
+            time: now(),
+        };
+        match database.lock() {
+            Ok(database_lock) => {
+                if let Err(what) = model::log_delivery(&*database_lock, &delivery) {
+                    error!(error = %what, "Failed to log webhook delivery");
+                }
+            }
+            Err(what) => error!(error = %what, "Mutex poisoned"),
+        }
+        if error.is_none() {
+            return;
+        }
+        if attempt < hook.attempts {
+            actix_rt::time::delay_for(Duration::from_secs(1 << (attempt - 1).min(10))).await;
+        }
+    }
+    warn!(event = %event, url = %hook.url, "Gave up delivering a webhook");
+}
@@ -0,0 +1,237 @@
+//! Bodies of the requests and responses of the REST interface, shared by the
+//! server and the client. Their JSON schemas end up in `/api/openapi.json`.
+
+use std::collections::HashMap;
+use std::hash::Hash;
+use std::str::FromStr;
+
+use schemars::JsonSchema;
+use serde::de::Error;
+use serde::{Deserialize, Deserializer, Serialize};
+
+/// Header through which enrolled voters present their credential
+pub const CREDENTIAL_HEADER: &str = "X-Voter-Credential";
+
+/// Header through which anonymous electors present their ballot secret
+pub const BALLOT_SECRET_HEADER: &str = "X-Ballot-Secret";
+
+#[derive(Serialize, Deserialize, Clone, Debug, JsonSchema)]
+pub struct BallotRow {
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub elector: Option<usize>,
+    /// Identifier of the ranked alternative
+    pub alternative: usize,
+    /// Lower bound of the score range
+    pub min: u64,
+    /// Higher bound of the score range
+    pub max: u64,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, JsonSchema)]
+pub struct AlternativeData {
+    pub id: i64,
+    pub name: String,
+    /// Markdown source of the description
+    pub description: String,
+    /// Description rendered to sanitized HTML
+    pub description_html: String,
+    pub icon: String,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, JsonSchema)]
+pub struct ElectionData {
+    pub title: Option<String>,
+    pub alternatives: Vec<AlternativeData>,
+    /// Ballot the elector already cast, empty if none
+    pub ballot: Vec<BallotRow>,
+    /// Number of votes the ballot counts for
+    pub weight: f64,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, JsonSchema)]
+pub struct SecretData {
+    pub secret: String,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, JsonSchema)]
+pub struct WeightData {
+    pub weight: f64,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, JsonSchema)]
+pub struct IconData {
+    pub icon: String,
+}
+
+#[derive(Serialize, Deserialize, Clone, Copy, Debug, JsonSchema)]
+pub struct ArrowData {
+    pub from: usize,
+    pub to: usize,
+}
+
+#[derive(Serialize, Clone, Debug, JsonSchema)]
+#[serde(untagged)]
+#[schemars(rename = "StrategyData")]
+pub enum StrategyData<V: Eq + Hash> {
+    Pure(V),
+    Mixed(HashMap<V, f64>),
+}
+
+impl<'de, V> Deserialize<'de> for StrategyData<V>
+where
+    V: Deserialize<'de> + Eq + Hash + FromStr,
+{
+    /// Untagged enums do not parse the keys of JSON objects, which are
+    /// always strings, as numbers
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        #[derive(Deserialize)]
+        #[serde(untagged)]
+        enum Strategy<V> {
+            Pure(V),
+            Mixed(HashMap<String, f64>),
+        }
+
+        match Strategy::<V>::deserialize(deserializer)? {
+            Strategy::Pure(alternative) => Ok(Self::Pure(alternative)),
+            Strategy::Mixed(probabilities) => {
+                let mut mixed = HashMap::new();
+                for (alternative, probability) in probabilities {
+                    match alternative.parse() {
+                        Ok(alternative) => mixed.insert(alternative, probability),
+                        Err(_) => {
+                            return Err(D::Error::custom(format!(
+                                "{} is not an alternative",
+                                alternative
+                            )))
+                        }
+                    };
+                }
+                Ok(Self::Mixed(mixed))
+            }
+        }
+    }
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, JsonSchema)]
+pub struct ResultData {
+    pub title: String,
+    pub alternatives: Vec<AlternativeData>,
+    pub arrows: Vec<ArrowData>,
+    pub strategy: Option<StrategyData<usize>>,
+    pub seats: usize,
+    /// First alternative drawn, once the election is closed
+    pub winner: Option<usize>,
+    /// Alternatives drawn in order, once the election is closed
+    pub winners: Vec<usize>,
+    /// Number of times winners were ever drawn, counting the draws discarded
+    /// by reopening the election
+    #[serde(default)]
+    pub draws: usize,
+}
+
+impl ResultData {
+    pub fn get_name(&self, alternative: usize) -> Option<String> {
+        self.alternatives
+            .iter()
+            .find(|a| a.id as usize == alternative)
+            .map(|a| a.name.to_string())
+    }
+
+    pub fn get_winner_names(&self) -> Vec<String> {
+        self.winners
+            .iter()
+            .filter_map(|&w| self.get_name(w))
+            .collect()
+    }
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, JsonSchema)]
+pub struct IssuedCredential {
+    pub name: String,
+    pub email: String,
+    pub credential: String,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, JsonSchema)]
+pub struct EnrolledElector {
+    pub name: String,
+    pub email: String,
+    pub group: Option<String>,
+    pub weight: f64,
+    pub voted: bool,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, JsonSchema)]
+pub struct Delivery {
+    pub url: String,
+    pub event: String,
+    pub attempt: u32,
+    pub status: Option<u16>,
+    pub error: Option<String>,
+    pub time: u64,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, Default, JsonSchema)]
+pub struct MailReport {
+    pub sent: usize,
+    pub failed: Vec<String>,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, JsonSchema)]
+pub struct TitleData {
+    pub title: String,
+}
+
+/// Name and description given to an alternative by an administrator
+#[derive(Serialize, Deserialize, Clone, Debug, JsonSchema)]
+pub struct AlternativeEdit {
+    pub name: String,
+    /// Markdown source of the description
+    #[serde(default)]
+    pub description: String,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, JsonSchema)]
+pub struct RankedCount {
+    pub alternative: usize,
+    /// Number of ballots giving a score to the alternative
+    pub ballots: usize,
+}
+
+/// Participation in the election so far, without the content of the ballots
+#[derive(Serialize, Deserialize, Clone, Debug, JsonSchema)]
+pub struct TurnoutData {
+    pub open: bool,
+    pub ballots: usize,
+    /// Sum of the weights of the ballots
+    pub weight: f64,
+    /// Number of voters on the roll, zero if the election has none
+    pub enrolled: usize,
+    /// Number of voters on the roll who voted
+    pub voted: usize,
+    pub ranked: Vec<RankedCount>,
+}
+
+/// Row of a ballot export, as CSV; the rows of one ballot share its number
+#[derive(Serialize, Deserialize, Clone, Debug)]
+pub struct BallotRecord {
+    pub ballot: usize,
+    pub weight: f64,
+    /// Name of the alternative, so that the export can be imported in
+    /// another data base
+    pub alternative: String,
+    pub min: u64,
+    pub max: u64,
+}
+
+/// Entry of the audit log. Its hash covers the hash of the previous entry, so
+/// that altering or removing an entry shows in every entry after it.
+#[derive(Serialize, Deserialize, Clone, Debug, JsonSchema)]
+pub struct AuditEntry {
+    pub id: i64,
+    pub time: u64,
+    pub event: String,
+    pub data: serde_json::Value,
+    /// Hexadecimal SHA-256 chaining the entry to the previous one
+    pub hash: String,
+}